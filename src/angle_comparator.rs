@@ -20,7 +20,9 @@ impl AngleComparator {
         }
 
         if abs_diff_eq!(a.x(), self.origin.x()) && abs_diff_eq!(b.x(), self.origin.x()) {
-            return if a.y() >= self.origin.y() || b.y() >= self.origin.y() {
+            return if a.y() == b.y() {
+                std::cmp::Ordering::Equal
+            } else if a.y() >= self.origin.y() || b.y() >= self.origin.y() {
                 if b.y() < a.y() {
                     std::cmp::Ordering::Less
                 } else {
@@ -38,11 +40,7 @@ impl AngleComparator {
         let det = cross(oa, ob);
 
         if abs_diff_eq!(det, 0.0) {
-            if Euclidean::distance(a, &self.origin) < Euclidean::distance(b, &self.origin) {
-                std::cmp::Ordering::Less
-            } else {
-                std::cmp::Ordering::Greater
-            }
+            Euclidean::distance(a, &self.origin).total_cmp(&Euclidean::distance(b, &self.origin))
         } else if det < 0.0 {
             std::cmp::Ordering::Less
         } else {
@@ -122,5 +120,13 @@ mod tests {
             angle_cmp.cmp(&point!(x: 0.0, y: 0.0), &point!(x: 0.0, y: 0.0)),
             std::cmp::Ordering::Less
         );
+        assert_eq!(
+            angle_cmp.cmp(&point!(x: 1.0, y: 1.0), &point!(x: 1.0, y: 1.0)),
+            std::cmp::Ordering::Equal
+        );
+        assert_eq!(
+            angle_cmp.cmp(&point!(x: 0.0, y: 2.0), &point!(x: 0.0, y: 2.0)),
+            std::cmp::Ordering::Equal
+        );
     }
 }
@@ -0,0 +1,161 @@
+#[cfg(feature = "geo-clipper")]
+use geo::orient::{Direction, Orient};
+use std::borrow::Cow;
+
+/// Way unions, intersections and differences of polygons are computed.
+///
+/// Clipper works on integer coordinates: they are multiplied by the factor and rounded, so a factor of 1000 keeps
+/// three decimals. Scenes in degrees of latitude and longitude need a much larger factor, while very large
+/// coordinates need a smaller one not to overflow.
+/// The pure Rust boolean operations of geo work on the coordinates themselves.
+/// Both give the same regions whatever the winding of the rings.
+///
+/// The default is Clipper with a factor of 1000 when the `geo-clipper` feature is enabled, which it is by default,
+/// and geo otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum BooleanBackend {
+    /// Clipper, with coordinates scaled by the factor.
+    #[cfg(feature = "geo-clipper")]
+    Clipper {
+        /// Factor by which coordinates are multiplied before being rounded to integers.
+        factor: f64,
+    },
+    /// Boolean operations of geo.
+    Geo,
+}
+
+impl Default for BooleanBackend {
+    #[cfg(feature = "geo-clipper")]
+    fn default() -> Self {
+        Self::Clipper { factor: 1000.0 }
+    }
+
+    #[cfg(not(feature = "geo-clipper"))]
+    fn default() -> Self {
+        Self::Geo
+    }
+}
+
+impl BooleanBackend {
+    /// Returns how far the result vertices can be moved by rounding.
+    pub(crate) fn precision(self) -> f64 {
+        match self {
+            #[cfg(feature = "geo-clipper")]
+            Self::Clipper { factor } => 1.0 / factor,
+            Self::Geo => 0.0,
+        }
+    }
+
+    /// Returns the union of the regions.
+    pub(crate) fn union(self, a: &impl Region, b: &impl Region) -> geo::MultiPolygon<f64> {
+        let (a, b) = (self.prepare(a), self.prepare(b));
+        match self {
+            #[cfg(feature = "geo-clipper")]
+            Self::Clipper { factor } => geo_clipper::Clipper::union(&*a, &*b, factor),
+            Self::Geo => geo::BooleanOps::union(&*a, &*b),
+        }
+    }
+
+    /// Returns the intersection of the regions.
+    pub(crate) fn intersection(self, a: &impl Region, b: &impl Region) -> geo::MultiPolygon<f64> {
+        let (a, b) = (self.prepare(a), self.prepare(b));
+        match self {
+            #[cfg(feature = "geo-clipper")]
+            Self::Clipper { factor } => geo_clipper::Clipper::intersection(&*a, &*b, factor),
+            Self::Geo => geo::BooleanOps::intersection(&*a, &*b),
+        }
+    }
+
+    /// Returns the part of the first region outside of the second one.
+    pub(crate) fn difference(self, a: &impl Region, b: &impl Region) -> geo::MultiPolygon<f64> {
+        let (a, b) = (self.prepare(a), self.prepare(b));
+        match self {
+            #[cfg(feature = "geo-clipper")]
+            Self::Clipper { factor } => geo_clipper::Clipper::difference(&*a, &*b, factor),
+            Self::Geo => geo::BooleanOps::difference(&*a, &*b),
+        }
+    }
+
+    /// Returns the region as a multi polygon the backend can work on.
+    ///
+    /// Clipper fills holes wound like the exterior, so the rings are given the default orientation.
+    fn prepare(self, region: &impl Region) -> Cow<'_, geo::MultiPolygon<f64>> {
+        let region = region.to_multi_polygon();
+        match self {
+            #[cfg(feature = "geo-clipper")]
+            Self::Clipper { .. } => Cow::Owned(region.orient(Direction::Default)),
+            Self::Geo => region,
+        }
+    }
+}
+
+/// Polygonal regions boolean operations apply to.
+pub(crate) trait Region {
+    fn to_multi_polygon(&self) -> Cow<'_, geo::MultiPolygon<f64>>;
+}
+
+impl Region for geo::Polygon<f64> {
+    fn to_multi_polygon(&self) -> Cow<'_, geo::MultiPolygon<f64>> {
+        Cow::Owned(geo::MultiPolygon(vec![self.clone()]))
+    }
+}
+
+impl Region for geo::MultiPolygon<f64> {
+    fn to_multi_polygon(&self) -> Cow<'_, geo::MultiPolygon<f64>> {
+        Cow::Borrowed(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{polygon, Area};
+
+    #[test]
+    fn keep_small_coordinates_with_geo() {
+        // a square of a hundredth of a degree
+        let a = polygon![(x: 2.35, y: 48.85), (x: 2.36, y: 48.85), (x: 2.36, y: 48.86), (x: 2.35, y: 48.86)];
+        let b = polygon![(x: 2.355, y: 48.85), (x: 2.365, y: 48.85), (x: 2.365, y: 48.86), (x: 2.355, y: 48.86)];
+
+        let union = BooleanBackend::Geo.union(&a, &b);
+        let intersection = BooleanBackend::Geo.intersection(&a, &b);
+        let difference = BooleanBackend::Geo.difference(&a, &b);
+
+        assert!((union.unsigned_area() - 1.5E-4).abs() < 1E-12);
+        assert!((intersection.unsigned_area() - 0.5E-4).abs() < 1E-12);
+        assert!((difference.unsigned_area() - 0.5E-4).abs() < 1E-12);
+    }
+
+    #[cfg(feature = "geo-clipper")]
+    #[test]
+    fn scale_coordinates_with_clipper() {
+        let a = polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0)];
+        let b = polygon![(x: 0.5, y: 0.0), (x: 1.5, y: 0.0), (x: 1.5, y: 1.0), (x: 0.5, y: 1.0)];
+
+        let union = BooleanBackend::Clipper { factor: 1E6 }.union(&a, &b);
+
+        assert!((union.unsigned_area() - 1.5).abs() < 1E-9);
+    }
+
+    #[cfg(feature = "geo-clipper")]
+    #[test]
+    fn agree_on_holes_whatever_their_winding() {
+        // the macro keeps the hole wound like the exterior
+        let room = polygon!(
+            exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+            interiors: [[(x: 4.0, y: 4.0), (x: 6.0, y: 4.0), (x: 6.0, y: 6.0), (x: 4.0, y: 6.0)]],
+        );
+        let half =
+            polygon![(x: 0.0, y: 0.0), (x: 5.0, y: 0.0), (x: 5.0, y: 10.0), (x: 0.0, y: 10.0)];
+
+        for backend in [
+            BooleanBackend::Clipper { factor: 1000.0 },
+            BooleanBackend::Geo,
+        ] {
+            assert!((backend.union(&room, &half).unsigned_area() - 98.0).abs() < 1E-9);
+            assert!((backend.intersection(&room, &half).unsigned_area() - 48.0).abs() < 1E-9);
+            assert!((backend.difference(&room, &half).unsigned_area() - 48.0).abs() < 1E-9);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::boolean::BooleanBackend;
+use crate::visibility::Visibility;
+use geo::Area;
+
+/// Coverage of a domain by the visibility polygons of several guards.
+///
+/// Every part is clipped to the domain and keeps all its disconnected components.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon};
+/// use geo_visibility::Coverage;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let coverage = Coverage::new(&[point!(x: 3.5, y: 0.5)], &domain, &domain);
+///
+/// assert!((coverage.covered_area() - 4.1).abs() < 1E-3);
+/// assert!((coverage.uncovered_area() - 2.9).abs() < 1E-3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    /// Visibility polygon of each guard, in the guards order.
+    pub visibilities: Vec<geo::Polygon<f64>>,
+    /// Part of the domain seen by at least one guard.
+    pub covered: geo::MultiPolygon<f64>,
+    /// Part of the domain seen by every guard, empty if there is no guard.
+    pub seen_by_all: geo::MultiPolygon<f64>,
+    /// Part of the domain seen by no guard.
+    pub uncovered: geo::MultiPolygon<f64>,
+}
+
+impl Coverage {
+    /// Computes the coverage of the domain by the guards amongst the obstacles.
+    pub fn new<T>(guards: &[geo::Point<f64>], obstacles: &T, domain: &geo::Polygon<f64>) -> Self
+    where
+        T: ?Sized,
+        geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+    {
+        Self::with_backend(guards, obstacles, domain, BooleanBackend::default())
+    }
+
+    /// Computes the coverage of the domain by the guards amongst the obstacles with the given boolean operations.
+    pub fn with_backend<T>(
+        guards: &[geo::Point<f64>],
+        obstacles: &T,
+        domain: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> Self
+    where
+        T: ?Sized,
+        geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+    {
+        let visibilities: Vec<_> = guards
+            .iter()
+            .map(|guard| guard.visibility(obstacles))
+            .collect();
+
+        let mut covered = geo::MultiPolygon(Vec::new());
+        let mut seen_by_all: Option<geo::MultiPolygon<f64>> = None;
+        for visibility in &visibilities {
+            let visibility = backend.intersection(visibility, domain);
+            covered = backend.union(&covered, &visibility);
+            seen_by_all = Some(match seen_by_all {
+                Some(seen_by_all) => backend.intersection(&seen_by_all, &visibility),
+                None => visibility,
+            });
+        }
+        let uncovered = backend.difference(domain, &covered);
+
+        Self {
+            visibilities,
+            covered,
+            seen_by_all: seen_by_all.unwrap_or_else(|| geo::MultiPolygon(Vec::new())),
+            uncovered,
+        }
+    }
+
+    /// Returns the area seen by at least one guard.
+    pub fn covered_area(&self) -> f64 {
+        self.covered.unsigned_area()
+    }
+
+    /// Returns the area seen by every guard.
+    pub fn seen_by_all_area(&self) -> f64 {
+        self.seen_by_all.unsigned_area()
+    }
+
+    /// Returns the area seen by no guard.
+    pub fn uncovered_area(&self) -> f64 {
+        self.uncovered.unsigned_area()
+    }
+
+    /// Returns the fraction of the domain seen by at least one guard.
+    pub fn covered_ratio(&self) -> f64 {
+        let total = self.covered_area() + self.uncovered_area();
+        if total > 0.0 {
+            self.covered_area() / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Face of a coverage arrangement, seen by the same set of guards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageFace {
+    /// Indices of the guards seeing the face, in increasing order.
+    pub guards: Vec<usize>,
+    /// Part of the domain covered by the face.
+    pub region: geo::MultiPolygon<f64>,
+}
+
+/// Overlay of the visibility polygons of several guards over a domain.
+///
+/// The domain is split into faces seen by the same set of guards, which gives the coverage depth of every region.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon, Area};
+/// use geo_visibility::CoverageArrangement;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+/// let guards = [point!(x: 3.5, y: 0.5), point!(x: 0.5, y: 3.5)];
+///
+/// let arrangement = CoverageArrangement::new(&guards, &domain, &domain);
+/// let depths = arrangement.by_count();
+///
+/// assert_eq!(depths.len(), 3);
+/// assert!((depths[2].unsigned_area() - 1.2).abs() < 1E-3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageArrangement {
+    guards_count: usize,
+    faces: Vec<CoverageFace>,
+    backend: BooleanBackend,
+}
+
+impl CoverageArrangement {
+    /// Overlays the visibility polygons of the guards amongst the obstacles over the domain.
+    pub fn new<T>(guards: &[geo::Point<f64>], obstacles: &T, domain: &geo::Polygon<f64>) -> Self
+    where
+        T: ?Sized,
+        geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+    {
+        Self::with_backend(guards, obstacles, domain, BooleanBackend::default())
+    }
+
+    /// Overlays the visibility polygons of the guards amongst the obstacles over the domain with the given boolean
+    /// operations, which are also used to merge the faces.
+    pub fn with_backend<T>(
+        guards: &[geo::Point<f64>],
+        obstacles: &T,
+        domain: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> Self
+    where
+        T: ?Sized,
+        geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+    {
+        let mut faces = vec![CoverageFace {
+            guards: Vec::new(),
+            region: geo::MultiPolygon(vec![domain.clone()]),
+        }];
+
+        for (index, guard) in guards.iter().enumerate() {
+            let visibility = guard.visibility(obstacles);
+            faces = faces
+                .into_iter()
+                .flat_map(|face| {
+                    let mut seen = face.guards.clone();
+                    seen.push(index);
+                    let inside = CoverageFace {
+                        guards: seen,
+                        region: backend.intersection(&face.region, &visibility),
+                    };
+                    let outside = CoverageFace {
+                        guards: face.guards,
+                        region: backend.difference(&face.region, &visibility),
+                    };
+                    std::iter::once(inside).chain(std::iter::once(outside))
+                })
+                .filter(|face| face.region.unsigned_area() > 0.0)
+                .collect();
+        }
+
+        Self {
+            guards_count: guards.len(),
+            faces,
+            backend,
+        }
+    }
+
+    /// Returns the faces of the arrangement.
+    pub fn faces(&self) -> &[CoverageFace] {
+        &self.faces
+    }
+
+    /// Returns the region seen by exactly `count` guards for each count from zero to the number of guards.
+    pub fn by_count(&self) -> Vec<geo::MultiPolygon<f64>> {
+        let mut regions = vec![geo::MultiPolygon(Vec::new()); self.guards_count + 1];
+        for face in &self.faces {
+            let region = &mut regions[face.guards.len()];
+            *region = self.backend.union(region, &face.region);
+        }
+        regions
+    }
+
+    /// Returns the region seen by at least `count` guards.
+    pub fn at_least(&self, count: usize) -> geo::MultiPolygon<f64> {
+        self.faces
+            .iter()
+            .filter(|face| face.guards.len() >= count)
+            .fold(geo::MultiPolygon(Vec::new()), |region, face| {
+                self.backend.union(&region, &face.region)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::{point, polygon};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn cover_with_a_single_guard() {
+        let domain = domain();
+        let coverage = Coverage::new(&[point!(x: 1.0, y: 5.0)], &domain, &domain);
+
+        assert_relative_eq!(coverage.covered_area(), 61.5, epsilon = 1E-2);
+        assert_relative_eq!(coverage.seen_by_all_area(), 61.5, epsilon = 1E-2);
+        assert_relative_eq!(coverage.uncovered_area(), 30.5, epsilon = 1E-2);
+    }
+
+    #[test]
+    fn cover_with_two_guards() {
+        let domain = domain();
+        let coverage = Coverage::new(
+            &[point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)],
+            &domain,
+            &domain,
+        );
+
+        assert_eq!(coverage.visibilities.len(), 2);
+        assert_relative_eq!(coverage.covered_area(), 88.0, epsilon = 1E-2);
+        assert_relative_eq!(coverage.seen_by_all_area(), 35.0, epsilon = 1E-2);
+        assert_relative_eq!(coverage.uncovered_area(), 4.0, epsilon = 1E-2);
+        assert_relative_eq!(coverage.covered_ratio(), 88.0 / 92.0, epsilon = 1E-3);
+        // the strips above and below the hole are hidden from both guards
+        assert_eq!(coverage.uncovered.0.len(), 2);
+    }
+
+    #[test]
+    fn cover_nothing_without_guard() {
+        let domain = domain();
+        let coverage = Coverage::new(&[], &domain, &domain);
+
+        assert!(coverage.covered.0.is_empty());
+        assert!(coverage.seen_by_all.0.is_empty());
+        assert_relative_eq!(coverage.uncovered_area(), 92.0, epsilon = 1E-2);
+    }
+
+    #[test]
+    fn split_the_domain_by_coverage_depth() {
+        let domain = domain();
+        let arrangement = CoverageArrangement::new(
+            &[point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)],
+            &domain,
+            &domain,
+        );
+
+        let depths = arrangement.by_count();
+        assert_eq!(depths.len(), 3);
+        assert_relative_eq!(depths[0].unsigned_area(), 4.0, epsilon = 1E-2);
+        assert_relative_eq!(depths[1].unsigned_area(), 53.0, epsilon = 1E-2);
+        assert_relative_eq!(depths[2].unsigned_area(), 35.0, epsilon = 1E-2);
+        assert_relative_eq!(
+            arrangement.at_least(1).unsigned_area(),
+            88.0,
+            epsilon = 1E-2
+        );
+    }
+
+    #[test]
+    fn agree_whatever_the_backend() {
+        let domain = domain();
+        let guards = [point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)];
+
+        let coverage = Coverage::with_backend(&guards, &domain, &domain, BooleanBackend::Geo);
+        let arrangement =
+            CoverageArrangement::with_backend(&guards, &domain, &domain, BooleanBackend::Geo);
+
+        assert_relative_eq!(coverage.covered_area(), 88.0, epsilon = 1E-2);
+        assert_relative_eq!(coverage.seen_by_all_area(), 35.0, epsilon = 1E-2);
+        assert_relative_eq!(
+            arrangement.by_count()[1].unsigned_area(),
+            53.0,
+            epsilon = 1E-2
+        );
+    }
+
+    #[test]
+    fn annotate_faces_with_guards() {
+        let domain = domain();
+        let arrangement = CoverageArrangement::new(
+            &[point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)],
+            &domain,
+            &domain,
+        );
+
+        let mut faces: Vec<_> = arrangement
+            .faces()
+            .iter()
+            .map(|face| (face.guards.clone(), face.region.unsigned_area()))
+            .collect();
+        faces.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(faces.len(), 4);
+        assert_eq!(faces[0].0, vec![]);
+        assert_eq!(faces[1].0, vec![0]);
+        assert_eq!(faces[2].0, vec![0, 1]);
+        assert_eq!(faces[3].0, vec![1]);
+        assert_relative_eq!(faces[1].1, 26.5, epsilon = 1E-2);
+        assert_relative_eq!(faces[3].1, 26.5, epsilon = 1E-2);
+    }
+}
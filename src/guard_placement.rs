@@ -0,0 +1,302 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::visibility::VisibilityPolygon;
+use crate::visibility_graph::ring_wedges;
+use geo::{Area, BoundingRect, Contains};
+
+/// Candidate positions for the guards.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardCandidates {
+    /// Vertices of the domain, moved slightly inside it.
+    Vertices,
+    /// Centres of the cells of a square grid lying inside the domain.
+    Grid {
+        /// Side length of the grid cells.
+        spacing: f64,
+    },
+    /// Points sampled uniformly inside the domain.
+    Random {
+        /// Number of points sampled.
+        count: usize,
+        /// Seed of the pseudorandom generator, the same seed gives the same points.
+        seed: u64,
+    },
+    /// Positions given by the caller.
+    Points(Vec<geo::Point<f64>>),
+}
+
+/// Error returned when the candidate positions cannot be generated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardCandidatesError {
+    /// The spacing of the grid is not positive and finite.
+    InvalidSpacing(f64),
+}
+
+impl std::fmt::Display for GuardCandidatesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuardCandidatesError::InvalidSpacing(spacing) => {
+                write!(f, "grid spacing {} is not positive and finite", spacing)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuardCandidatesError {}
+
+impl GuardCandidates {
+    /// Returns the candidate positions inside the domain.
+    ///
+    /// Fails if the spacing of a grid is not positive and finite.
+    pub fn points(
+        &self,
+        domain: &geo::Polygon<f64>,
+    ) -> Result<Vec<geo::Point<f64>>, GuardCandidatesError> {
+        if let GuardCandidates::Grid { spacing } = self {
+            if !(*spacing > 0.0 && spacing.is_finite()) {
+                return Err(GuardCandidatesError::InvalidSpacing(*spacing));
+            }
+        }
+        let bounds = match domain.bounding_rect() {
+            Some(bounds) => bounds,
+            None => return Ok(Vec::new()),
+        };
+        Ok(match self {
+            GuardCandidates::Vertices => {
+                // vertices are moved inside so that the walls meeting at them still block the view
+                let offset = 1E-9 * bounds.width().max(bounds.height());
+                domain
+                    .rings()
+                    .iter()
+                    .flat_map(ring_wedges)
+                    .map(|wedge| wedge.vertex + wedge.free_direction() * offset)
+                    .collect()
+            }
+            GuardCandidates::Grid { spacing } => {
+                let columns = (bounds.width() / spacing).ceil() as usize;
+                let rows = (bounds.height() / spacing).ceil() as usize;
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (row, column)))
+                    .map(|(row, column)| {
+                        geo::Point::new(
+                            bounds.min().x + (column as f64 + 0.5) * spacing,
+                            bounds.min().y + (row as f64 + 0.5) * spacing,
+                        )
+                    })
+                    .filter(|point| domain.contains(point))
+                    .collect()
+            }
+            GuardCandidates::Random { count, seed } => {
+                let mut state = *seed;
+                let mut points = Vec::with_capacity(*count);
+                // rejection sampling gives up on domains covering a tiny part of their bounds
+                let mut attempts = 0;
+                while points.len() < *count && attempts < 1000 * (count + 1) {
+                    attempts += 1;
+                    let point = geo::Point::new(
+                        bounds.min().x + unit_random(&mut state) * bounds.width(),
+                        bounds.min().y + unit_random(&mut state) * bounds.height(),
+                    );
+                    if domain.contains(&point) {
+                        points.push(point);
+                    }
+                }
+                points
+            }
+            GuardCandidates::Points(points) => points
+                .iter()
+                .filter(|point| domain.contains(*point))
+                .copied()
+                .collect(),
+        })
+    }
+}
+
+/// Returns a number uniformly distributed in `[0, 1)` using the SplitMix64 generator.
+fn unit_random(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Set of guards seeing a domain, chosen amongst candidates with a greedy art gallery heuristic.
+///
+/// At each step the candidate seeing the largest uncovered area is added,
+/// until the target fraction of the domain is covered or no candidate sees anything new.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::{GuardCandidates, GuardPlacement};
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let placement = GuardPlacement::greedy(&domain, &GuardCandidates::Vertices, 0.999).unwrap();
+///
+/// assert_eq!(placement.guards.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardPlacement {
+    /// Chosen guards, in the order they were picked.
+    pub guards: Vec<geo::Point<f64>>,
+    /// Part of the domain seen by no guard.
+    pub uncovered: geo::MultiPolygon<f64>,
+    /// Fraction of the domain seen by at least one guard.
+    pub covered_ratio: f64,
+}
+
+impl GuardPlacement {
+    /// Picks guards amongst the candidates until the target fraction of the domain is covered.
+    ///
+    /// Fails if the candidates cannot be generated, see [`GuardCandidates::points`].
+    pub fn greedy(
+        domain: &geo::Polygon<f64>,
+        candidates: &GuardCandidates,
+        target_ratio: f64,
+    ) -> Result<Self, GuardCandidatesError> {
+        Self::greedy_with_backend(domain, candidates, target_ratio, BooleanBackend::default())
+    }
+
+    /// Picks guards amongst the candidates until the target fraction of the domain is covered, with the given
+    /// boolean operations.
+    ///
+    /// Fails if the candidates cannot be generated, see [`GuardCandidates::points`].
+    pub fn greedy_with_backend(
+        domain: &geo::Polygon<f64>,
+        candidates: &GuardCandidates,
+        target_ratio: f64,
+        backend: BooleanBackend,
+    ) -> Result<Self, GuardCandidatesError> {
+        let total = domain.unsigned_area();
+        let segments = ObstacleSegments::new(domain);
+        let mut candidates: Vec<_> = candidates
+            .points(domain)?
+            .into_iter()
+            .map(|point| {
+                let visibility = VisibilityPolygon::among(point, &segments).polygon;
+                let visibility = backend.intersection(&visibility, domain);
+                (point, visibility)
+            })
+            .collect();
+
+        let mut guards = Vec::new();
+        let mut uncovered = geo::MultiPolygon(vec![domain.clone()]);
+        let mut covered_ratio = 0.0;
+
+        while covered_ratio < target_ratio && !candidates.is_empty() {
+            let (best, gain) = candidates
+                .iter()
+                .enumerate()
+                .map(|(index, (_, visibility))| {
+                    (
+                        index,
+                        backend.intersection(&uncovered, visibility).unsigned_area(),
+                    )
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap();
+            if gain <= 1E-9 * total {
+                break;
+            }
+
+            let (guard, visibility) = candidates.swap_remove(best);
+            guards.push(guard);
+            uncovered = backend.difference(&uncovered, &visibility);
+            covered_ratio = 1.0 - uncovered.unsigned_area() / total;
+        }
+
+        Ok(Self {
+            guards,
+            uncovered,
+            covered_ratio,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn generate_candidates_inside_the_domain() {
+        let domain = domain();
+
+        assert_eq!(GuardCandidates::Vertices.points(&domain).unwrap().len(), 8);
+        assert_eq!(
+            GuardCandidates::Grid { spacing: 1.0 }
+                .points(&domain)
+                .unwrap()
+                .len(),
+            92
+        );
+        let random = GuardCandidates::Random { count: 50, seed: 3 };
+        let points = random.points(&domain).unwrap();
+        assert_eq!(points.len(), 50);
+        assert!(points.iter().all(|point| domain.contains(point)));
+        assert_eq!(points, random.points(&domain).unwrap());
+    }
+
+    #[test]
+    fn cover_the_whole_domain() {
+        let domain = domain();
+        let placement = GuardPlacement::greedy(&domain, &GuardCandidates::Vertices, 1.0).unwrap();
+
+        assert!(placement.covered_ratio > 0.999);
+        assert!(placement.uncovered.unsigned_area() < 1E-1);
+        assert!(placement.guards.len() <= 4);
+    }
+
+    #[test]
+    fn stop_at_the_target_ratio() {
+        let domain = domain();
+        let placement =
+            GuardPlacement::greedy(&domain, &GuardCandidates::Grid { spacing: 1.0 }, 0.5).unwrap();
+
+        assert_eq!(placement.guards.len(), 1);
+        assert!(placement.covered_ratio >= 0.5);
+        assert!(placement.uncovered.unsigned_area() <= 46.0);
+    }
+
+    #[test]
+    fn reject_a_null_grid_spacing() {
+        let candidates = GuardCandidates::Grid { spacing: 0.0 };
+
+        assert_eq!(
+            candidates.points(&domain()),
+            Err(GuardCandidatesError::InvalidSpacing(0.0))
+        );
+        assert_eq!(
+            GuardPlacement::greedy(&domain(), &candidates, 1.0),
+            Err(GuardCandidatesError::InvalidSpacing(0.0))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::visibility::{EdgeSource, VisibilityPolygon};
+use geo::{Area, Centroid, Distance, Euclidean, Length};
+
+/// Isovist measures of a visibility polygon.
+///
+/// Edges are told apart with their [`EdgeSource`]: windows are the edges along which the view goes past an obstacle,
+/// the real boundary is made of the edges lying on obstacles.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::{IsovistMetrics, VisibilityPolygon};
+///
+/// let room = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 10.0, y: 0.0),
+///     (x: 10.0, y: 10.0),
+///     (x: 0.0, y: 10.0),
+/// ];
+///
+/// let visibility = VisibilityPolygon::new(geo::Point::new(5.0, 5.0), &room);
+/// let metrics = IsovistMetrics::new(&visibility);
+///
+/// assert!((metrics.area - 100.0).abs() < 1E-9);
+/// assert_eq!(metrics.occlusivity, 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsovistMetrics {
+    /// Area of the isovist.
+    pub area: f64,
+    /// Length of the isovist boundary.
+    pub perimeter: f64,
+    /// Distance from the viewpoint to the closest point of the boundary.
+    pub min_radial: f64,
+    /// Distance from the viewpoint to the farthest point of the boundary.
+    pub max_radial: f64,
+    /// Distance from the viewpoint to the boundary averaged over the view directions.
+    pub mean_radial: f64,
+    /// Ratio of the area to the area of a disc with the same perimeter, `1` for a disc.
+    pub compactness: f64,
+    /// Total length of the windows.
+    pub occlusivity: f64,
+    /// Distance from the viewpoint to the centroid of the isovist.
+    pub drift: f64,
+    /// Length of the real boundary divided by the length of the windows, infinite without window.
+    pub real_to_window_ratio: f64,
+}
+
+impl IsovistMetrics {
+    /// Computes the isovist measures of the visibility polygon.
+    pub fn new(visibility: &VisibilityPolygon) -> Self {
+        let viewpoint = visibility.viewpoint;
+        let area = visibility.polygon.unsigned_area();
+        let perimeter = visibility.polygon.exterior().length::<Euclidean>();
+
+        let mut min_radial = f64::INFINITY;
+        let mut max_radial: f64 = 0.0;
+        let mut radial_integral = 0.0;
+        let mut angle = 0.0;
+        let mut occlusivity = 0.0;
+        let mut real_boundary = 0.0;
+        for (line, source) in visibility.lines() {
+            min_radial = min_radial.min(Euclidean::distance(&viewpoint, &line));
+            max_radial = max_radial.max(Euclidean::distance(&viewpoint, &line.start_point()));
+
+            let length = line.length::<Euclidean>();
+            match source {
+                EdgeSource::Obstacle(_) => real_boundary += length,
+                EdgeSource::Window(_) => occlusivity += length,
+                EdgeSource::Open => {}
+            }
+
+            // the distance along the edge is h / cos(t), whose integral over the angle t is h * asinh(tan(t))
+            if length > 0.0 {
+                let direction = (line.end_point() - line.start_point()) / length;
+                let start = line.start_point() - viewpoint;
+                let end = line.end_point() - viewpoint;
+                let h = start.x() * direction.y() - start.y() * direction.x();
+                let h = h.abs();
+                if h > 0.0 {
+                    let (s_start, s_end) = (start.dot(direction), end.dot(direction));
+                    radial_integral += h * ((s_end / h).asinh() - (s_start / h).asinh());
+                    angle += (s_end / h).atan() - (s_start / h).atan();
+                }
+            }
+        }
+
+        let drift = visibility
+            .polygon
+            .centroid()
+            .map_or(0.0, |centroid| Euclidean::distance(&viewpoint, &centroid));
+
+        Self {
+            area,
+            perimeter,
+            min_radial: if min_radial.is_finite() {
+                min_radial
+            } else {
+                0.0
+            },
+            max_radial,
+            mean_radial: if angle > 0.0 {
+                radial_integral / angle
+            } else {
+                0.0
+            },
+            compactness: if perimeter > 0.0 {
+                4.0 * std::f64::consts::PI * area / (perimeter * perimeter)
+            } else {
+                0.0
+            },
+            occlusivity,
+            drift,
+            real_to_window_ratio: if occlusivity > 0.0 {
+                real_boundary / occlusivity
+            } else {
+                f64::INFINITY
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::{point, polygon};
+
+    #[test]
+    fn measure_a_square_room() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 10.0, y: 0.0),
+            (x: 10.0, y: 10.0),
+            (x: 0.0, y: 10.0),
+        ];
+        let metrics = IsovistMetrics::new(&VisibilityPolygon::new(point!(x: 5.0, y: 5.0), &room));
+
+        assert_relative_eq!(metrics.area, 100.0, epsilon = 1E-9);
+        assert_relative_eq!(metrics.perimeter, 40.0, epsilon = 1E-9);
+        assert_relative_eq!(metrics.min_radial, 5.0, epsilon = 1E-9);
+        assert_relative_eq!(metrics.max_radial, 50.0_f64.sqrt(), epsilon = 1E-9);
+        assert_relative_eq!(
+            metrics.mean_radial,
+            40.0 * 1.0_f64.asinh() / (2.0 * std::f64::consts::PI),
+            epsilon = 1E-9
+        );
+        assert_relative_eq!(
+            metrics.compactness,
+            std::f64::consts::FRAC_PI_4,
+            epsilon = 1E-9
+        );
+        assert_relative_eq!(metrics.drift, 0.0, epsilon = 1E-9);
+        assert_eq!(metrics.occlusivity, 0.0);
+        assert_eq!(metrics.real_to_window_ratio, f64::INFINITY);
+    }
+
+    #[test]
+    fn measure_windows_behind_a_hole() {
+        let domain = polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        );
+        let metrics = IsovistMetrics::new(&VisibilityPolygon::new(point!(x: 1.0, y: 5.0), &domain));
+
+        // the windows go from (3, 4) to (10, 0.5) and from (3, 6) to (10, 9.5)
+        assert_relative_eq!(metrics.occlusivity, 2.0 * 61.25_f64.sqrt(), epsilon = 1E-9);
+        assert_relative_eq!(metrics.area, 61.5, epsilon = 1E-9);
+        assert_relative_eq!(metrics.min_radial, 1.0, epsilon = 1E-9);
+        assert_relative_eq!(
+            metrics.real_to_window_ratio,
+            (metrics.perimeter - metrics.occlusivity) / metrics.occlusivity,
+            epsilon = 1E-9
+        );
+        assert!(metrics.drift > 1.0);
+    }
+}
@@ -0,0 +1,446 @@
+use crate::isovist::IsovistMetrics;
+use crate::obstacles::ObstacleSegments;
+use crate::visibility::VisibilityPolygon;
+use geo::{BoundingRect, Contains};
+use std::io::Write;
+
+/// Value written in ESRI ASCII grids for the cells without measure.
+const NODATA: f64 = -9999.0;
+
+/// Error returned when an isovist field cannot be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IsovistFieldError {
+    /// The cell size is not positive and finite.
+    InvalidCellSize(f64),
+}
+
+impl std::fmt::Display for IsovistFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsovistFieldError::InvalidCellSize(size) => {
+                write!(f, "cell size {} is not positive and finite", size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IsovistFieldError {}
+
+/// Isovist measures evaluated at the cell centres of a square grid covering a domain.
+///
+/// Rows go upwards from the bottom of the domain bounds, columns go rightwards from its left side.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::IsovistField;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let field = IsovistField::new(&domain, 1.0).unwrap();
+/// let mut grid = Vec::new();
+/// field.write_esri_ascii(&mut grid, |metrics| metrics.area).unwrap();
+///
+/// assert_eq!((field.rows, field.columns), (4, 4));
+/// assert!(field.cells[2][2].is_none());
+/// assert!(String::from_utf8(grid).unwrap().starts_with("ncols 4\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsovistField {
+    /// Domain covered by the grid.
+    pub domain: geo::Polygon<f64>,
+    /// Lower left corner of the grid.
+    pub origin: geo::Coord<f64>,
+    /// Side length of the cells.
+    pub cell_size: f64,
+    /// Number of rows of the grid.
+    pub rows: usize,
+    /// Number of columns of the grid.
+    pub columns: usize,
+    /// Measures of each cell indexed by row then column, `None` for cells whose centre is outside the domain.
+    pub cells: Vec<Vec<Option<IsovistMetrics>>>,
+}
+
+/// Cell of an adaptive refinement of an isovist field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsovistSample {
+    /// Centre of the cell, where the measures are evaluated.
+    pub center: geo::Point<f64>,
+    /// Side length of the cell.
+    pub size: f64,
+    /// Measures at the centre of the cell.
+    pub metrics: IsovistMetrics,
+}
+
+impl IsovistField {
+    /// Evaluates the isovist measures at the centre of every cell lying in the domain.
+    ///
+    /// Fails if the cell size is not positive and finite.
+    pub fn new(domain: &geo::Polygon<f64>, cell_size: f64) -> Result<Self, IsovistFieldError> {
+        if !(cell_size > 0.0 && cell_size.is_finite()) {
+            return Err(IsovistFieldError::InvalidCellSize(cell_size));
+        }
+        let bounds = match domain.bounding_rect() {
+            Some(bounds) => bounds,
+            None => {
+                return Ok(Self {
+                    domain: domain.clone(),
+                    origin: geo::Coord { x: 0.0, y: 0.0 },
+                    cell_size,
+                    rows: 0,
+                    columns: 0,
+                    cells: Vec::new(),
+                })
+            }
+        };
+        let columns = (bounds.width() / cell_size).ceil() as usize;
+        let rows = (bounds.height() / cell_size).ceil() as usize;
+        let segments = ObstacleSegments::new(domain);
+
+        let mut field = Self {
+            domain: domain.clone(),
+            origin: bounds.min(),
+            cell_size,
+            rows,
+            columns,
+            cells: Vec::with_capacity(rows),
+        };
+        field.cells = (0..rows)
+            .map(|row| {
+                (0..columns)
+                    .map(|column| evaluate(domain, &segments, field.cell_center(row, column)))
+                    .collect()
+            })
+            .collect();
+        Ok(field)
+    }
+
+    /// Returns the centre of the cell.
+    pub fn cell_center(&self, row: usize, column: usize) -> geo::Point<f64> {
+        geo::Point::new(
+            self.origin.x + (column as f64 + 0.5) * self.cell_size,
+            self.origin.y + (row as f64 + 0.5) * self.cell_size,
+        )
+    }
+
+    /// Returns one measure for every cell, indexed by row then column.
+    pub fn values<F>(&self, metric: F) -> Vec<Vec<Option<f64>>>
+    where
+        F: Fn(&IsovistMetrics) -> f64,
+    {
+        self.cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.as_ref().map(&metric)).collect())
+            .collect()
+    }
+
+    /// Writes one measure as an ESRI ASCII grid.
+    ///
+    /// Cells outside the domain and non finite values are written as `-9999`.
+    pub fn write_esri_ascii<W, F>(&self, mut writer: W, metric: F) -> std::io::Result<()>
+    where
+        W: Write,
+        F: Fn(&IsovistMetrics) -> f64,
+    {
+        writeln!(writer, "ncols {}", self.columns)?;
+        writeln!(writer, "nrows {}", self.rows)?;
+        writeln!(writer, "xllcorner {}", self.origin.x)?;
+        writeln!(writer, "yllcorner {}", self.origin.y)?;
+        writeln!(writer, "cellsize {}", self.cell_size)?;
+        writeln!(writer, "NODATA_value {}", NODATA)?;
+        // the grid is written from the top row down
+        for row in self.values(metric).iter().rev() {
+            let line: Vec<_> = row
+                .iter()
+                .map(|value| match value {
+                    Some(value) if value.is_finite() => value.to_string(),
+                    _ => NODATA.to_string(),
+                })
+                .collect();
+            writeln!(writer, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes every measure of the cells lying in the domain as CSV, one line per cell centre.
+    ///
+    /// Non finite values are written as empty fields.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "x,y,area,perimeter,min_radial,max_radial,mean_radial,compactness,occlusivity,drift,real_to_window_ratio"
+        )?;
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if let Some(metrics) = cell {
+                    let center = self.cell_center(row, column);
+                    let fields: Vec<_> = [
+                        center.x(),
+                        center.y(),
+                        metrics.area,
+                        metrics.perimeter,
+                        metrics.min_radial,
+                        metrics.max_radial,
+                        metrics.mean_radial,
+                        metrics.compactness,
+                        metrics.occlusivity,
+                        metrics.drift,
+                        metrics.real_to_window_ratio,
+                    ]
+                    .iter()
+                    .map(|value| {
+                        if value.is_finite() {
+                            value.to_string()
+                        } else {
+                            String::new()
+                        }
+                    })
+                    .collect();
+                    writeln!(writer, "{}", fields.join(","))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Refines the grid with a quadtree where the measure changes sharply.
+    ///
+    /// A cell differing by more than the tolerance from one of its neighbours is split into four, and so are the
+    /// resulting quadrants differing from each other, at most `max_depth` times.
+    /// Cells that are not split are returned as they are.
+    pub fn refine<F>(&self, metric: F, tolerance: f64, max_depth: usize) -> Vec<IsovistSample>
+    where
+        F: Fn(&IsovistMetrics) -> f64,
+    {
+        let values = self.values(&metric);
+        let segments = ObstacleSegments::new(&self.domain);
+        let mut samples = Vec::new();
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                let metrics = match cell {
+                    Some(metrics) => *metrics,
+                    None => continue,
+                };
+                let value = metric(&metrics);
+                let neighbors = [
+                    (row.wrapping_sub(1), column),
+                    (row + 1, column),
+                    (row, column.wrapping_sub(1)),
+                    (row, column + 1),
+                ];
+                let sharp = neighbors.iter().any(|(row, column)| {
+                    values
+                        .get(*row)
+                        .and_then(|row| row.get(*column))
+                        .copied()
+                        .flatten()
+                        .is_some_and(|other| (other - value).abs() > tolerance)
+                });
+
+                let sample = IsovistSample {
+                    center: self.cell_center(row, column),
+                    size: self.cell_size,
+                    metrics,
+                };
+                if sharp && max_depth > 0 {
+                    subdivide(
+                        &self.domain,
+                        &segments,
+                        sample,
+                        &metric,
+                        tolerance,
+                        max_depth,
+                        &mut samples,
+                    );
+                } else {
+                    samples.push(sample);
+                }
+            }
+        }
+        samples
+    }
+}
+
+/// Returns the measures at the point, `None` if it is outside the domain whose segments are given.
+fn evaluate(
+    domain: &geo::Polygon<f64>,
+    segments: &ObstacleSegments,
+    point: geo::Point<f64>,
+) -> Option<IsovistMetrics> {
+    if domain.contains(&point) {
+        Some(IsovistMetrics::new(&VisibilityPolygon::among(
+            point, segments,
+        )))
+    } else {
+        None
+    }
+}
+
+/// Splits the cell into its quadrants lying in the domain, and keeps splitting those differing from each other.
+fn subdivide<F>(
+    domain: &geo::Polygon<f64>,
+    segments: &ObstacleSegments,
+    cell: IsovistSample,
+    metric: &F,
+    tolerance: f64,
+    depth: usize,
+    samples: &mut Vec<IsovistSample>,
+) where
+    F: Fn(&IsovistMetrics) -> f64,
+{
+    let size = cell.size / 2.0;
+    let quadrants: Vec<_> = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .filter_map(|(dx, dy)| {
+            let center = cell.center + geo::Point::new(dx * size / 2.0, dy * size / 2.0);
+            evaluate(domain, segments, center).map(|metrics| IsovistSample {
+                center,
+                size,
+                metrics,
+            })
+        })
+        .collect();
+
+    let (min, max) = quadrants
+        .iter()
+        .map(|quadrant| metric(&quadrant.metrics))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+    for quadrant in quadrants {
+        if depth > 1 && max - min > tolerance {
+            subdivide(
+                domain,
+                segments,
+                quadrant,
+                metric,
+                tolerance,
+                depth - 1,
+                samples,
+            );
+        } else {
+            samples.push(quadrant);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::polygon;
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn skip_cells_outside_free_space() {
+        let field = IsovistField::new(&domain(), 1.0).unwrap();
+
+        assert_eq!((field.rows, field.columns), (10, 10));
+        let free = field.cells.iter().flatten().filter(|cell| cell.is_some());
+        assert_eq!(free.count(), 92);
+        assert!(field.cells[4][4].is_none());
+        assert_relative_eq!(
+            field.cells[5][0].unwrap().area,
+            field.cells[5][9].unwrap().area,
+            epsilon = 1E-9
+        );
+    }
+
+    #[test]
+    fn write_an_esri_ascii_grid() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 2.0, y: 0.0),
+            (x: 2.0, y: 1.0),
+            (x: 1.0, y: 1.0),
+            (x: 1.0, y: 2.0),
+            (x: 0.0, y: 2.0),
+        ];
+        let field = IsovistField::new(&room, 1.0).unwrap();
+        let mut grid = Vec::new();
+        field
+            .write_esri_ascii(&mut grid, |metrics| metrics.area)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(grid).unwrap(),
+            "ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\nNODATA_value -9999\n2.5 -9999\n3 2.5\n"
+        );
+    }
+
+    #[test]
+    fn write_a_csv_table() {
+        let field = IsovistField::new(&domain(), 1.0).unwrap();
+        let mut table = Vec::new();
+        field.write_csv(&mut table).unwrap();
+
+        let table = String::from_utf8(table).unwrap();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 93);
+        assert!(lines[0].starts_with("x,y,area,"));
+        assert!(lines[1].starts_with("0.5,0.5,"));
+        assert!(lines.iter().all(|line| line.split(',').count() == 11));
+    }
+
+    #[test]
+    fn leave_non_finite_values_empty_in_csv() {
+        let room = polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0)];
+        let mut field = IsovistField::new(&room, 1.0).unwrap();
+        if let Some(metrics) = &mut field.cells[0][0] {
+            metrics.max_radial = f64::INFINITY;
+            metrics.drift = f64::NAN;
+        }
+        let mut table = Vec::new();
+        field.write_csv(&mut table).unwrap();
+
+        let table = String::from_utf8(table).unwrap();
+        let fields: Vec<_> = table.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(fields.len(), 11);
+        assert_eq!(fields[5], "");
+        assert_eq!(fields[9], "");
+        assert!(!table.contains("inf") && !table.contains("NaN"));
+    }
+
+    #[test]
+    fn reject_a_null_cell_size() {
+        assert_eq!(
+            IsovistField::new(&domain(), 0.0),
+            Err(IsovistFieldError::InvalidCellSize(0.0))
+        );
+    }
+
+    #[test]
+    fn refine_where_the_measure_changes_sharply() {
+        let field = IsovistField::new(&domain(), 1.0).unwrap();
+        let samples = field.refine(|metrics| metrics.area, 10.0, 2);
+
+        assert!(samples.len() > 92);
+        assert!(samples.iter().any(|sample| sample.size == 0.25));
+        let area: f64 = samples.iter().map(|sample| sample.size * sample.size).sum();
+        assert_relative_eq!(area, 92.0, epsilon = 1E-9);
+    }
+}
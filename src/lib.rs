@@ -33,11 +33,57 @@
 //! [`visibility`]: visibility/trait.Visibility.html#method.visibility
 
 mod angle_comparator;
+mod boolean;
 mod comparable_line;
+mod coverage;
+mod guard_placement;
+mod isovist;
+mod isovist_field;
+#[cfg(feature = "rstar")]
+mod obstacle_index;
+mod obstacles;
 mod orientation;
 mod ray;
+mod sampled_visibility;
+mod segment_soup;
+mod shadows;
+mod shortest_path;
+mod shortest_path_map;
+mod star_polygon;
 mod utils;
 mod visibility;
 mod visibility_event;
+mod visibility_graph;
+mod visibility_graph_analysis;
+mod visibility_matrix;
+mod visible_fraction;
+#[cfg(feature = "rstar")]
+mod visible_nearest;
+mod watchman_route;
+mod weak_visibility;
 
-pub use visibility::Visibility;
+pub use boolean::BooleanBackend;
+pub use coverage::{Coverage, CoverageArrangement, CoverageFace};
+pub use guard_placement::{GuardCandidates, GuardCandidatesError, GuardPlacement};
+pub use isovist::IsovistMetrics;
+pub use isovist_field::{IsovistField, IsovistFieldError, IsovistSample};
+#[cfg(feature = "rstar")]
+pub use obstacle_index::ObstacleIndex;
+pub use obstacles::{ObstacleSegments, Obstacles, SegmentOrigin, Solids, Walls};
+pub use sampled_visibility::{RegionSampling, SampledVisibility, SampledVisibilityError};
+pub use segment_soup::{clean_segments, close_gaps, ClosedGap, GapClosing};
+pub use shadows::{shadows, Shadows};
+pub use shortest_path::{shortest_path, ShortestPathError};
+pub use shortest_path_map::{ShortestPathMap, ShortestPathRegion};
+pub use visibility::{
+    polygon_visibility, polygon_visibility_regions, CornerPolicy, EdgeSource, MountSide,
+    Visibility, VisibilityPolygon,
+};
+pub use visibility_graph::VisibilityGraph;
+pub use visibility_graph_analysis::{VgaMeasures, VisibilityGraphAnalysis};
+pub use visibility_matrix::{MatrixStrategy, VisibilityMatrix};
+pub use visible_fraction::{visible_fraction, Target, VisibleFraction};
+#[cfg(feature = "rstar")]
+pub use visible_nearest::visible_nearest_neighbors;
+pub use watchman_route::{WatchmanRoute, WatchmanRouteError};
+pub use weak_visibility::polyline_visibility;
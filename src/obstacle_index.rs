@@ -0,0 +1,266 @@
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::segment_soup::segment_key;
+use crate::visibility::VisibilityPolygon;
+use rstar::{RTree, AABB};
+use std::collections::HashSet;
+
+/// Obstacle segments stored in an R-tree so that visibility queries only sweep the segments near the viewpoint.
+///
+/// Queries are restricted to a region of interest: segments outside of it are culled, those crossing its boundary
+/// are clipped, and the boundary itself bounds the view.
+/// Segments are split where they cross once when they are indexed, so that queries sweep them as they are.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon, Area};
+/// use geo_visibility::ObstacleIndex;
+///
+/// let domain = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 1000.0, y: 0.0), (x: 1000.0, y: 1000.0), (x: 0.0, y: 1000.0)],
+///     interiors: [[(x: 3.0, y: 4.0), (x: 7.0, y: 4.0), (x: 7.0, y: 6.0), (x: 3.0, y: 6.0)]],
+/// );
+/// let index = ObstacleIndex::new(&domain);
+///
+/// let visibility = index.visibility(point!(x: 50.0, y: 50.0), 10.0);
+///
+/// assert!((visibility.unsigned_area() - 400.0).abs() < 1E-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObstacleIndex {
+    tree: RTree<geo::Line<f64>>,
+}
+
+impl ObstacleIndex {
+    /// Indexes the segments of the obstacles, split where they cross.
+    pub fn new<T: Obstacles + ?Sized>(obstacles: &T) -> Self {
+        let pieces = ObstacleSegments::new(obstacles)
+            .pieces
+            .into_iter()
+            .map(|(piece, _)| piece)
+            .collect();
+        Self {
+            tree: RTree::bulk_load(pieces),
+        }
+    }
+
+    /// Returns the R-tree of the obstacle segments, which meet only at their endpoints.
+    pub fn tree(&self) -> &RTree<geo::Line<f64>> {
+        &self.tree
+    }
+
+    /// Returns the obstacle segments clipped to the region.
+    pub fn segments_in(&self, region: &geo::Rect<f64>) -> Vec<geo::Line<f64>> {
+        let envelope = AABB::from_corners(region.min().into(), region.max().into());
+        self.tree
+            .locate_in_envelope_intersecting(&envelope)
+            .filter_map(|segment| clip(segment, region))
+            .filter(|segment| segment.start != segment.end)
+            .collect()
+    }
+
+    /// Computes the visibility polygon of the viewpoint within the square of half side `range` centred on it.
+    pub fn visibility(&self, viewpoint: geo::Point<f64>, range: f64) -> geo::Polygon<f64> {
+        let offset = geo::Coord { x: range, y: range };
+        self.visibility_in(
+            viewpoint,
+            &geo::Rect::new(viewpoint.0 - offset, viewpoint.0 + offset),
+        )
+    }
+
+    /// Computes the visibility polygon of the viewpoint within the region of interest.
+    pub fn visibility_in(
+        &self,
+        viewpoint: geo::Point<f64>,
+        region: &geo::Rect<f64>,
+    ) -> geo::Polygon<f64> {
+        let mut segments = self.segments_in(region);
+
+        // the region sides are split where clipped segments end on them, so that segments only meet at endpoints
+        let corners: Vec<geo::Coord<f64>> = region.to_polygon().exterior().0[..4].to_vec();
+        let mut sides = Vec::new();
+        for side in 0..4 {
+            let (start, end) = (corners[side], corners[(side + 1) % 4]);
+            let along =
+                |point: &geo::Coord<f64>| (point.x - start.x).abs() + (point.y - start.y).abs();
+            let mut points = vec![start, end];
+            for segment in &segments {
+                for point in [segment.start, segment.end] {
+                    if on_side(&point, &start, &end) {
+                        points.push(point);
+                    }
+                }
+            }
+            points.sort_by(|a, b| along(a).total_cmp(&along(b)));
+            points.dedup();
+            sides.extend(
+                points
+                    .windows(2)
+                    .map(|pair| geo::Line::new(pair[0], pair[1])),
+            );
+        }
+        // segments running along the region sides are kept once
+        let clipped: HashSet<_> = segments.iter().map(segment_key).collect();
+        segments.extend(
+            sides
+                .into_iter()
+                .filter(|side| !clipped.contains(&segment_key(side))),
+        );
+
+        VisibilityPolygon::among(viewpoint, &ObstacleSegments::from_pieces(segments)).polygon
+    }
+}
+
+/// The segments of the tree are expected to meet only at their endpoints, as those indexed by [`ObstacleIndex::new`].
+impl From<RTree<geo::Line<f64>>> for ObstacleIndex {
+    fn from(tree: RTree<geo::Line<f64>>) -> Self {
+        Self { tree }
+    }
+}
+
+/// Returns whether the point lies on the axis aligned side.
+fn on_side(point: &geo::Coord<f64>, start: &geo::Coord<f64>, end: &geo::Coord<f64>) -> bool {
+    if start.x == end.x {
+        point.x == start.x && point.y >= start.y.min(end.y) && point.y <= start.y.max(end.y)
+    } else {
+        point.y == start.y && point.x >= start.x.min(end.x) && point.x <= start.x.max(end.x)
+    }
+}
+
+/// Clips the segment to the rectangle with the Liang-Barsky algorithm.
+fn clip(segment: &geo::Line<f64>, region: &geo::Rect<f64>) -> Option<geo::Line<f64>> {
+    let delta = segment.delta();
+    let (mut t0, mut t1): (f64, f64) = (0.0, 1.0);
+    for (p, q) in [
+        (-delta.x, segment.start.x - region.min().x),
+        (delta.x, region.max().x - segment.start.x),
+        (-delta.y, segment.start.y - region.min().y),
+        (delta.y, region.max().y - segment.start.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    // endpoints moved onto the region boundary are snapped to it exactly
+    let snap = |t: f64, original: geo::Coord<f64>| {
+        if t == 0.0 || t == 1.0 {
+            return original;
+        }
+        let mut point = segment.start + delta * t;
+        for bound in [region.min().x, region.max().x] {
+            if (point.x - bound).abs() <= 1E-9 * region.width().max(1.0) {
+                point.x = bound;
+            }
+        }
+        for bound in [region.min().y, region.max().y] {
+            if (point.y - bound).abs() <= 1E-9 * region.height().max(1.0) {
+                point.y = bound;
+            }
+        }
+        point
+    };
+    Some(geo::Line::new(
+        snap(t0, segment.start),
+        snap(t1, segment.end),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::BooleanBackend;
+    use crate::visibility::Visibility;
+    use approx::assert_relative_eq;
+    use geo::{point, polygon, Area};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn cull_segments_outside_the_region() {
+        let index = ObstacleIndex::new(&domain());
+        let region = geo::Rect::new(geo::Coord { x: 2.0, y: 2.0 }, geo::Coord { x: 5.0, y: 5.0 });
+
+        let segments = index.segments_in(&region);
+
+        assert_eq!(segments.len(), 2);
+        assert!(segments.contains(&geo::Line::new(
+            geo::Coord { x: 3.0, y: 4.0 },
+            geo::Coord { x: 5.0, y: 4.0 }
+        )));
+        assert!(segments.contains(&geo::Line::new(
+            geo::Coord { x: 3.0, y: 5.0 },
+            geo::Coord { x: 3.0, y: 4.0 }
+        )));
+    }
+
+    #[test]
+    fn agree_with_the_full_visibility_within_range() {
+        let domain = domain();
+        let index = ObstacleIndex::new(&domain);
+        let viewpoint = point!(x: 1.0, y: 5.0);
+
+        let limited = index.visibility(viewpoint, 3.0);
+        let region = geo::Rect::new(
+            geo::Coord { x: -2.0, y: 2.0 },
+            geo::Coord { x: 4.0, y: 8.0 },
+        );
+        let expected = BooleanBackend::default()
+            .intersection(&viewpoint.visibility(&domain), &region.to_polygon());
+
+        assert_relative_eq!(
+            limited.unsigned_area(),
+            expected.unsigned_area(),
+            epsilon = 1E-6
+        );
+        assert_relative_eq!(
+            index.visibility(viewpoint, 100.0).unsigned_area(),
+            viewpoint.visibility(&domain).unsigned_area(),
+            epsilon = 1E-6
+        );
+    }
+
+    #[test]
+    fn sweep_crossing_segments_split_once() {
+        let mut obstacles: Vec<geo::Geometry<f64>> = vec![domain().into()];
+        obstacles.push(
+            geo::Line::new(geo::Coord { x: 1.0, y: 1.0 }, geo::Coord { x: 9.0, y: 3.0 }).into(),
+        );
+        obstacles.push(
+            geo::Line::new(geo::Coord { x: 1.0, y: 3.0 }, geo::Coord { x: 9.0, y: 1.0 }).into(),
+        );
+        let index = ObstacleIndex::new(&obstacles);
+        let viewpoint = point!(x: 5.0, y: 8.0);
+
+        assert_eq!(index.tree().size(), 12);
+        assert_relative_eq!(
+            index.visibility(viewpoint, 100.0).unsigned_area(),
+            viewpoint.visibility(&obstacles).unsigned_area(),
+            epsilon = 1E-6
+        );
+    }
+}
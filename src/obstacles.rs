@@ -0,0 +1,560 @@
+use crate::segment_soup::{segment_key, split_segments};
+use geo::{Area, BoundingRect, Contains, Winding};
+use std::collections::{HashMap, HashSet};
+
+/// Geometries whose boundaries block the view.
+pub trait Obstacles {
+    /// Returns the line segments blocking the view.
+    fn segments(&self) -> Vec<geo::Line<f64>>;
+
+    /// Returns the rings bounding the free space, oriented so that free space lies on their left.
+    ///
+    /// The free space is inside the rings enclosed by an even number of other rings, such as a room, and outside
+    /// the others, such as the holes of a room or the furniture standing in it.
+    /// Obstacles standing in an open space are wrapped in [`Solids`] to turn this around.
+    /// Line segments are walls without inside so they return no ring.
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        Vec::new()
+    }
+
+    /// Returns the boundaries of each part of the obstacles, such as the rings of each polygon.
+    ///
+    /// Their edges are the [`Obstacles::segments`], in the same order.
+    /// By default, every segment is a boundary of a single part.
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        vec![self
+            .segments()
+            .into_iter()
+            .map(|segment| geo::LineString(vec![segment.start, segment.end]))
+            .collect()]
+    }
+}
+
+/// Returns the edges of the boundaries of the parts.
+fn part_segments(parts: &[Vec<geo::LineString<f64>>]) -> Vec<geo::Line<f64>> {
+    parts
+        .iter()
+        .flatten()
+        .flat_map(geo::LineString::lines)
+        .collect()
+}
+
+/// Orients the rings so that free space lies on their left, according to the number of rings enclosing them.
+///
+/// Rings are expected not to cross, so that only larger rings whose bounding box covers a ring may enclose it.
+fn orient_rings(mut rings: Vec<geo::LineString<f64>>) -> Vec<geo::LineString<f64>> {
+    let areas: Vec<_> = rings
+        .iter()
+        .map(|ring| geo::Polygon::new(ring.clone(), Vec::new()))
+        .collect();
+    let extents: Vec<_> = rings.iter().map(|ring| ring.bounding_rect()).collect();
+    let sizes: Vec<_> = areas.iter().map(|area| area.unsigned_area()).collect();
+    // candidate containers are found amongst the larger rings
+    let mut order: Vec<_> = (0..rings.len()).collect();
+    order.sort_by(|a, b| sizes[*b].total_cmp(&sizes[*a]));
+    let covers = |outer: Option<geo::Rect<f64>>, inner: Option<geo::Rect<f64>>| match (outer, inner)
+    {
+        (Some(outer), Some(inner)) => {
+            outer.min().x <= inner.min().x
+                && outer.min().y <= inner.min().y
+                && outer.max().x >= inner.max().x
+                && outer.max().y >= inner.max().y
+        }
+        _ => false,
+    };
+    let depths: Vec<_> = (0..rings.len())
+        .map(|i| {
+            order
+                .iter()
+                .take_while(|j| sizes[**j] >= sizes[i])
+                .filter(|j| **j != i && covers(extents[**j], extents[i]))
+                .filter(|j| rings[i].points().any(|point| areas[**j].contains(&point)))
+                .count()
+        })
+        .collect();
+    for (ring, depth) in rings.iter_mut().zip(depths) {
+        if depth % 2 == 0 {
+            ring.make_ccw_winding();
+        } else {
+            ring.make_cw_winding();
+        }
+    }
+    rings
+}
+
+impl Obstacles for geo::Line<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        vec![vec![geo::LineString(vec![self.start, self.end])]]
+    }
+}
+
+impl Obstacles for geo::LineString<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        vec![vec![self.clone()]]
+    }
+}
+
+/// Line strings are the parts of a multi line string.
+impl Obstacles for geo::MultiLineString<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.0
+            .iter()
+            .map(|line_string| vec![line_string.clone()])
+            .collect()
+    }
+}
+
+/// The exterior is the first boundary of a polygon, followed by its interiors.
+impl Obstacles for geo::Polygon<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        orient_rings(
+            std::iter::once(self.exterior())
+                .chain(self.interiors())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        vec![std::iter::once(self.exterior())
+            .chain(self.interiors())
+            .cloned()
+            .collect()]
+    }
+}
+
+impl Obstacles for geo::MultiPolygon<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        orient_rings(self.0.iter().flat_map(Obstacles::rings).collect())
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.0.iter().flat_map(Obstacles::parts).collect()
+    }
+}
+
+/// Rectangles are oriented like polygons: a room on their own, furniture when standing in another ring.
+impl Obstacles for geo::Rect<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.to_polygon().segments()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        self.to_polygon().rings()
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.to_polygon().parts()
+    }
+}
+
+/// Triangles are oriented like polygons: a room on their own, furniture when standing in another ring.
+impl Obstacles for geo::Triangle<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.to_polygon().segments()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        self.to_polygon().rings()
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.to_polygon().parts()
+    }
+}
+
+/// Points have no segment and do not block the view.
+impl Obstacles for geo::Geometry<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        part_segments(&self.parts())
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        match self {
+            geo::Geometry::Polygon(polygon) => polygon.rings(),
+            geo::Geometry::MultiPolygon(multi_polygon) => multi_polygon.rings(),
+            geo::Geometry::GeometryCollection(collection) => collection.rings(),
+            geo::Geometry::Rect(rect) => rect.rings(),
+            geo::Geometry::Triangle(triangle) => triangle.rings(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        match self {
+            geo::Geometry::Point(_) | geo::Geometry::MultiPoint(_) => Vec::new(),
+            geo::Geometry::Line(line) => line.parts(),
+            geo::Geometry::LineString(line_string) => line_string.parts(),
+            geo::Geometry::MultiLineString(multi_line_string) => multi_line_string.parts(),
+            geo::Geometry::Polygon(polygon) => polygon.parts(),
+            geo::Geometry::MultiPolygon(multi_polygon) => multi_polygon.parts(),
+            geo::Geometry::GeometryCollection(collection) => collection.parts(),
+            geo::Geometry::Rect(rect) => rect.parts(),
+            geo::Geometry::Triangle(triangle) => triangle.parts(),
+        }
+    }
+}
+
+impl Obstacles for geo::GeometryCollection<f64> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.0.as_slice().segments()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        self.0.as_slice().rings()
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.0.as_slice().parts()
+    }
+}
+
+impl<T: Obstacles> Obstacles for [T] {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.iter().flat_map(Obstacles::segments).collect()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        orient_rings(self.iter().flat_map(Obstacles::rings).collect())
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.iter().flat_map(Obstacles::parts).collect()
+    }
+}
+
+impl<T: Obstacles> Obstacles for Vec<T> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.as_slice().segments()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        self.as_slice().rings()
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.as_slice().parts()
+    }
+}
+
+/// Obstacle line segments given by any source which can be iterated over, such as an iterator adaptor.
+///
+/// # Example
+///
+/// ```
+/// use geo::{Area, Coord, Line};
+/// use geo_visibility::{Visibility, Walls};
+///
+/// let corners = [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)];
+/// let walls = Walls(corners.windows(2).map(|pair| {
+///     Line::new(Coord::from(pair[0]), Coord::from(pair[1]))
+/// }));
+///
+/// let visibility = geo::Point::new(1.0, 1.0).visibility(&walls);
+///
+/// assert!((visibility.unsigned_area() - 16.0).abs() < 1E-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Walls<I>(pub I);
+
+impl<I> Obstacles for Walls<I>
+where
+    I: IntoIterator<Item = geo::Line<f64>> + Clone,
+{
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.0.clone().into_iter().collect()
+    }
+}
+
+/// Obstacles standing in an open space, such as building footprints: the inside of their rings is solid.
+///
+/// On their own, the rings of obstacles bound rooms whose inside is free, see [`Obstacles::rings`].
+/// Wrapped in `Solids`, the rings enclosed by an even number of other rings are solid instead,
+/// so that the free space lies around the obstacles and inside their holes, such as courtyards.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon, Euclidean, Length};
+/// use geo_visibility::{shortest_path, Solids};
+///
+/// let buildings = geo::MultiPolygon(vec![
+///     polygon![(x: 2.0, y: -1.0), (x: 4.0, y: -1.0), (x: 4.0, y: 1.0), (x: 2.0, y: 1.0)],
+///     polygon![(x: 6.0, y: -1.0), (x: 8.0, y: -1.0), (x: 8.0, y: 1.0), (x: 6.0, y: 1.0)],
+/// ]);
+///
+/// let path = shortest_path(point!(x: 0.0, y: 0.0), point!(x: 10.0, y: 0.0), &Solids(buildings)).unwrap();
+///
+/// assert!((path.length::<Euclidean>() - (6.0 + 2.0 * 5.0_f64.sqrt())).abs() < 1E-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Solids<T>(pub T);
+
+impl<T: Obstacles> Obstacles for Solids<T> {
+    fn segments(&self) -> Vec<geo::Line<f64>> {
+        self.0.segments()
+    }
+
+    fn rings(&self) -> Vec<geo::LineString<f64>> {
+        self.0
+            .rings()
+            .into_iter()
+            .map(|mut ring| {
+                ring.0.reverse();
+                ring
+            })
+            .collect()
+    }
+
+    fn parts(&self) -> Vec<Vec<geo::LineString<f64>>> {
+        self.0.parts()
+    }
+}
+
+/// Position of an edge within the boundaries returned by [`Obstacles::parts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SegmentOrigin {
+    /// Index of the polygon, or of the part for other geometries.
+    pub polygon: usize,
+    /// Index of the ring within the polygon, the exterior being the first one.
+    pub ring: usize,
+    /// Index of the edge along the ring.
+    pub edge: usize,
+}
+
+/// Distinct segments of obstacles, along with the edges they come from.
+///
+/// This is how obstacles are turned into segments before computing visibility:
+/// segments given several times, in either direction, are kept once and segments of null length are dropped.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::{ObstacleSegments, SegmentOrigin};
+///
+/// // two rooms sharing a wall
+/// let rooms = geo::MultiPolygon(vec![
+///     polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0)],
+///     polygon![(x: 1.0, y: 0.0), (x: 2.0, y: 0.0), (x: 2.0, y: 1.0), (x: 1.0, y: 1.0)],
+/// ]);
+///
+/// let segments = ObstacleSegments::new(&rooms);
+///
+/// assert_eq!(segments.segments.len(), 7);
+/// assert_eq!(
+///     segments.origins[1],
+///     vec![
+///         SegmentOrigin { polygon: 0, ring: 0, edge: 1 },
+///         SegmentOrigin { polygon: 1, ring: 0, edge: 3 },
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObstacleSegments {
+    /// Distinct segments, in the order they first appear in.
+    pub segments: Vec<geo::Line<f64>>,
+    /// Edges each segment comes from, in the order they appear in.
+    pub origins: Vec<Vec<SegmentOrigin>>,
+    /// Pieces of the segments meeting only at their endpoints, along with the index of the segment they come from.
+    pub(crate) pieces: Vec<(geo::Line<f64>, usize)>,
+    /// Segments a viewpoint may be mounted on, along the oriented rings or in their own direction for the others.
+    pub(crate) walls: Vec<geo::Line<f64>>,
+}
+
+impl ObstacleSegments {
+    /// Extracts the distinct segments of the obstacles.
+    ///
+    /// The segments are split and the rings oriented once, so that they can be shared by many viewpoints,
+    /// see [`VisibilityPolygon::among`].
+    ///
+    /// [`VisibilityPolygon::among`]: crate::VisibilityPolygon::among
+    pub fn new<T: Obstacles + ?Sized>(obstacles: &T) -> Self {
+        let mut indices = HashMap::new();
+        let mut segments = Vec::new();
+        let mut origins: Vec<Vec<SegmentOrigin>> = Vec::new();
+        for (polygon, boundaries) in obstacles.parts().iter().enumerate() {
+            for (ring, boundary) in boundaries.iter().enumerate() {
+                for (edge, segment) in boundary.lines().enumerate() {
+                    if segment.start == segment.end {
+                        continue;
+                    }
+                    let origin = SegmentOrigin {
+                        polygon,
+                        ring,
+                        edge,
+                    };
+                    let index = *indices.entry(segment_key(&segment)).or_insert_with(|| {
+                        segments.push(segment);
+                        origins.push(Vec::new());
+                        segments.len() - 1
+                    });
+                    origins[index].push(origin);
+                }
+            }
+        }
+        // the sweep needs segments meeting only at their endpoints, they are split once for all the viewpoints
+        let pieces = split_segments(&segments);
+        // walls along the rings follow their orientation, the loose segments keep their own direction
+        let rings: Vec<_> = obstacles
+            .rings()
+            .iter()
+            .flat_map(|ring| ring.lines())
+            .collect();
+        let along_rings: HashSet<_> = rings.iter().map(segment_key).collect();
+        let walls = rings
+            .iter()
+            .chain(
+                segments
+                    .iter()
+                    .filter(|segment| !along_rings.contains(&segment_key(segment))),
+            )
+            .copied()
+            .collect();
+        Self {
+            segments,
+            origins,
+            pieces,
+            walls,
+        }
+    }
+}
+
+impl ObstacleSegments {
+    /// Takes segments meeting only at their endpoints as they are, without merging nor splitting them.
+    ///
+    /// Each segment is its own piece and comes from no edge.
+    #[cfg(feature = "rstar")]
+    pub(crate) fn from_pieces(segments: Vec<geo::Line<f64>>) -> Self {
+        Self {
+            origins: vec![Vec::new(); segments.len()],
+            pieces: segments.iter().copied().zip(0..).collect(),
+            walls: segments.clone(),
+            segments,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    fn hollow_square(offset: f64) -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: offset, y: 0.0),
+                (x: offset + 4.0, y: 0.0),
+                (x: offset + 4.0, y: 4.0),
+                (x: offset, y: 4.0),
+            ],
+            interiors: [[
+                (x: offset + 1.0, y: 1.0),
+                (x: offset + 2.0, y: 1.0),
+                (x: offset + 2.0, y: 2.0),
+                (x: offset + 1.0, y: 2.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn extract_each_hole_once() {
+        let obstacles = geo::MultiPolygon(vec![
+            hollow_square(0.0),
+            hollow_square(10.0),
+            hollow_square(20.0),
+        ]);
+
+        let segments = ObstacleSegments::new(&obstacles);
+
+        assert_eq!(obstacles.segments().len(), 24);
+        assert_eq!(segments.segments, obstacles.segments());
+        assert_eq!(
+            segments.origins[22],
+            vec![SegmentOrigin {
+                polygon: 2,
+                ring: 1,
+                edge: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn split_crossing_segments_for_the_sweep() {
+        let walls = [
+            geo::Line::new(
+                geo::Coord { x: -1.0, y: 0.0 },
+                geo::Coord { x: 1.0, y: 0.0 },
+            ),
+            geo::Line::new(
+                geo::Coord { x: 0.0, y: -1.0 },
+                geo::Coord { x: 0.0, y: 1.0 },
+            ),
+        ];
+
+        let segments = ObstacleSegments::new(&walls[..]);
+
+        assert_eq!(segments.segments, walls.to_vec());
+        let sources: Vec<_> = segments.pieces.iter().map(|(_, index)| *index).collect();
+        assert_eq!(sources, vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn turn_rooms_into_solids() {
+        let courtyard = hollow_square(0.0);
+
+        let rooms = courtyard.rings();
+        let solids = Solids(courtyard).rings();
+
+        assert!(rooms[0].is_ccw() && rooms[1].is_cw());
+        assert!(solids[0].is_cw() && solids[1].is_ccw());
+    }
+
+    #[test]
+    fn merge_duplicated_segments() {
+        let walls = [
+            geo::Line::new(geo::Coord { x: 0.0, y: 0.0 }, geo::Coord { x: 1.0, y: 0.0 }),
+            geo::Line::new(geo::Coord { x: 1.0, y: 1.0 }, geo::Coord { x: 1.0, y: 1.0 }),
+            geo::Line::new(geo::Coord { x: 1.0, y: 0.0 }, geo::Coord { x: 0.0, y: 0.0 }),
+        ];
+
+        let segments = ObstacleSegments::new(&walls[..]);
+
+        assert_eq!(segments.segments, vec![walls[0]]);
+        assert_eq!(
+            segments.origins[0],
+            vec![
+                SegmentOrigin {
+                    polygon: 0,
+                    ring: 0,
+                    edge: 0
+                },
+                SegmentOrigin {
+                    polygon: 2,
+                    ring: 0,
+                    edge: 0
+                },
+            ]
+        );
+    }
+}
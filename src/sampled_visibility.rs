@@ -0,0 +1,295 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::visibility::{EdgeSource, VisibilityPolygon};
+use geo::{BoundingRect, Contains, Distance, Euclidean};
+
+/// Largest number of samples the spacing may fit along the width or the height of the viewer.
+const MAX_SAMPLES_PER_SIDE: f64 = 1024.0;
+
+/// Error returned when the viewer cannot be sampled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SampledVisibilityError {
+    /// The spacing fits more than 1024 times along the width or the height of the viewer.
+    SpacingTooSmall(f64),
+}
+
+impl std::fmt::Display for SampledVisibilityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SampledVisibilityError::SpacingTooSmall(spacing) => {
+                write!(f, "spacing {} is too small for the viewer", spacing)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SampledVisibilityError {}
+
+/// Points of a polygon viewer from which visibility polygons are computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionSampling {
+    /// Largest distance between two consecutive samples along the rings, which is also the side of the interior
+    /// grid cells. Only the ring vertices are sampled when it is not positive.
+    ///
+    /// It must fit at most 1024 times along the width and the height of the viewer.
+    pub max_spacing: f64,
+    /// Whether the centres of the grid cells lying in the viewer are sampled.
+    pub interior: bool,
+    /// Whether the hole rings are sampled along with the exterior.
+    pub holes: bool,
+    /// Boolean operations merging the visibility polygons of the samples.
+    pub backend: BooleanBackend,
+}
+
+impl RegionSampling {
+    /// Samples the exterior, the holes and the interior with the given spacing, and merges the visibility polygons
+    /// with the default boolean operations.
+    pub fn new(max_spacing: f64) -> Self {
+        Self {
+            max_spacing,
+            interior: true,
+            holes: true,
+            backend: BooleanBackend::default(),
+        }
+    }
+}
+
+/// Approximation of the region seen from a polygon by the union of the visibility polygons of sample points.
+///
+/// A point seen from the inside of the viewer is also seen from where the line of sight leaves it, so the rings
+/// matter most: the interior samples only help when the rings are coarsely sampled.
+/// The approximation misses the slivers swept by the windows while the viewpoint moves between two samples, whose
+/// area is estimated by `error_bound`. It decreases linearly with the spacing.
+///
+/// # Example
+///
+/// ```
+/// use geo::{polygon, Area};
+/// use geo_visibility::{RegionSampling, SampledVisibility};
+///
+/// let domain = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+///     interiors: [[(x: 4.0, y: 4.0), (x: 6.0, y: 4.0), (x: 6.0, y: 6.0), (x: 4.0, y: 6.0)]],
+/// );
+/// let viewer = polygon![(x: 1.0, y: 1.0), (x: 3.0, y: 1.0), (x: 3.0, y: 3.0), (x: 1.0, y: 3.0)];
+///
+/// let coarse = SampledVisibility::new(&viewer, &domain, RegionSampling::new(1.0)).unwrap();
+/// let fine = SampledVisibility::new(&viewer, &domain, RegionSampling::new(0.25)).unwrap();
+///
+/// assert!(fine.region.unsigned_area() >= coarse.region.unsigned_area() - 1E-6);
+/// assert!(fine.error_bound < coarse.error_bound);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampledVisibility {
+    /// Points the visibility polygons are computed from, ring samples first.
+    pub samples: Vec<geo::Point<f64>>,
+    /// Union of the visibility polygons of the samples.
+    pub region: geo::MultiPolygon<f64>,
+    /// Estimate of the area seen from the viewer but missed by the samples.
+    ///
+    /// It only accounts for the sampled rings.
+    pub error_bound: f64,
+}
+
+impl SampledVisibility {
+    /// Approximates the region seen from the viewer amongst the obstacles.
+    ///
+    /// Fails if the spacing is positive but fits more than 1024 times along the width or the height of the viewer.
+    pub fn new<T: Obstacles + ?Sized>(
+        viewer: &geo::Polygon<f64>,
+        obstacles: &T,
+        sampling: RegionSampling,
+    ) -> Result<Self, SampledVisibilityError> {
+        if sampling.max_spacing > 0.0 {
+            let extent = viewer
+                .bounding_rect()
+                .map_or(0.0, |bounds| bounds.width().max(bounds.height()));
+            if extent / sampling.max_spacing > MAX_SAMPLES_PER_SIDE {
+                return Err(SampledVisibilityError::SpacingTooSmall(
+                    sampling.max_spacing,
+                ));
+            }
+        }
+        let rings: Vec<_> = if sampling.holes {
+            std::iter::once(viewer.exterior())
+                .chain(viewer.interiors())
+                .collect()
+        } else {
+            vec![viewer.exterior()]
+        };
+
+        let mut samples = Vec::new();
+        let mut largest_gap: f64 = 0.0;
+        for ring in rings {
+            for line in ring.lines() {
+                let length = distance(&line.start, &line.end);
+                let count = if sampling.max_spacing > 0.0 {
+                    (length / sampling.max_spacing).ceil().max(1.0) as usize
+                } else {
+                    1
+                };
+                largest_gap = largest_gap.max(length / count as f64);
+                samples.extend((0..count).map(|i| {
+                    geo::Point::from(line.start + line.delta() * (i as f64 / count as f64))
+                }));
+            }
+        }
+        let ring_samples = samples.len();
+        if sampling.interior && sampling.max_spacing > 0.0 {
+            samples.extend(grid_samples(viewer, sampling.max_spacing));
+        }
+
+        // a point of the rings is at most half the largest gap away from a sample
+        let shift = largest_gap / 2.0;
+        let backend = sampling.backend;
+        let segments = ObstacleSegments::new(obstacles);
+        let mut region = geo::MultiPolygon(Vec::new());
+        let mut error_bound = 0.0;
+        for (index, sample) in samples.iter().enumerate() {
+            let visibility = VisibilityPolygon::among(*sample, &segments);
+            if index < ring_samples {
+                error_bound += swept_area(&visibility, shift);
+            }
+            region = backend.union(&region, &visibility.polygon);
+        }
+
+        Ok(Self {
+            samples,
+            region,
+            error_bound,
+        })
+    }
+}
+
+/// Returns the centres of the cells of a square grid covering the viewer which lie inside it.
+fn grid_samples(viewer: &geo::Polygon<f64>, cell_size: f64) -> Vec<geo::Point<f64>> {
+    let bounds = match viewer.bounding_rect() {
+        Some(bounds) => bounds,
+        None => return Vec::new(),
+    };
+    let columns = (bounds.width() / cell_size).ceil() as usize;
+    let rows = (bounds.height() / cell_size).ceil() as usize;
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).map(move |column| {
+                geo::Point::new(
+                    bounds.min().x + (column as f64 + 0.5) * cell_size,
+                    bounds.min().y + (row as f64 + 0.5) * cell_size,
+                )
+            })
+        })
+        .filter(|point| viewer.contains(point))
+        .collect()
+}
+
+/// Returns the area swept by the windows of the visibility polygon when its viewpoint moves by the given shift.
+///
+/// A window pivots around the vertex it goes past, by an angle of at most the shift over the distance to the
+/// vertex.
+fn swept_area(visibility: &VisibilityPolygon, shift: f64) -> f64 {
+    let viewpoint = visibility.viewpoint.0;
+    visibility
+        .lines()
+        .filter(|(_, source)| matches!(source, EdgeSource::Window(_)))
+        .map(|(edge, _)| {
+            let (near, far) = {
+                let (a, b) = (
+                    distance(&edge.start, &viewpoint),
+                    distance(&edge.end, &viewpoint),
+                );
+                (a.min(b), a.max(b))
+            };
+            if near > 0.0 {
+                (shift / near).min(std::f64::consts::PI) * (far - near).powi(2) / 2.0
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+fn distance(a: &geo::Coord<f64>, b: &geo::Coord<f64>) -> f64 {
+    Euclidean::distance(geo::Point::from(*a), geo::Point::from(*b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visibility::Visibility;
+    use geo::{polygon, Area};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 4.0, y: 4.0),
+                (x: 6.0, y: 4.0),
+                (x: 6.0, y: 6.0),
+                (x: 4.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn bound_the_missed_area() {
+        let domain = domain();
+        let viewer =
+            polygon![(x: 1.0, y: 1.0), (x: 3.0, y: 1.0), (x: 3.0, y: 3.0), (x: 1.0, y: 3.0)];
+        let exact = viewer.exterior().visibility(&domain).unsigned_area();
+
+        for spacing in [0.0, 1.0, 0.25] {
+            let sampled =
+                SampledVisibility::new(&viewer, &domain, RegionSampling::new(spacing)).unwrap();
+            let area = sampled.region.unsigned_area();
+
+            assert!(area <= exact + 1E-2);
+            assert!(exact <= area + sampled.error_bound + 1E-2);
+        }
+    }
+
+    #[test]
+    fn reject_a_spacing_too_small_for_the_viewer() {
+        let viewer =
+            polygon![(x: 1.0, y: 1.0), (x: 3.0, y: 1.0), (x: 3.0, y: 3.0), (x: 1.0, y: 3.0)];
+
+        assert_eq!(
+            SampledVisibility::new(&viewer, &domain(), RegionSampling::new(f64::MIN_POSITIVE)),
+            Err(SampledVisibilityError::SpacingTooSmall(f64::MIN_POSITIVE))
+        );
+    }
+
+    #[test]
+    fn choose_the_samples() {
+        let domain = domain();
+        let viewer = polygon!(
+            exterior: [(x: 1.0, y: 1.0), (x: 3.0, y: 1.0), (x: 3.0, y: 3.0), (x: 1.0, y: 3.0)],
+            interiors: [[(x: 1.5, y: 1.5), (x: 2.5, y: 1.5), (x: 2.5, y: 2.5), (x: 1.5, y: 2.5)]],
+        );
+
+        let vertices = SampledVisibility::new(&viewer, &domain, RegionSampling::new(0.0)).unwrap();
+        let exterior = SampledVisibility::new(
+            &viewer,
+            &domain,
+            RegionSampling {
+                max_spacing: 1.0,
+                interior: false,
+                holes: false,
+                backend: BooleanBackend::Geo,
+            },
+        )
+        .unwrap();
+        let everything =
+            SampledVisibility::new(&viewer, &domain, RegionSampling::new(1.0)).unwrap();
+
+        assert_eq!(vertices.samples.len(), 8);
+        assert_eq!(exterior.samples.len(), 8);
+        // the centres of the grid cells are the corners of the hole, outside of the viewer
+        assert_eq!(everything.samples.len(), 12);
+    }
+}
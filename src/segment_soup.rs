@@ -0,0 +1,454 @@
+use crate::utils::approx_equal;
+use geo::line_intersection::{line_intersection, LineIntersection};
+use std::collections::HashSet;
+
+/// Turns a soup of segments into segments meeting only at their endpoints.
+///
+/// Segments are split where they cross or touch each other, collinear overlaps are kept once
+/// and zero-length segments are removed. The visibility sweep relies on this property and applies it to its input.
+///
+/// # Example
+///
+/// ```
+/// use geo::{Coord, Line};
+/// use geo_visibility::clean_segments;
+///
+/// let walls = [
+///     Line::new(Coord { x: -1.0, y: 0.0 }, Coord { x: 1.0, y: 0.0 }),
+///     Line::new(Coord { x: 0.0, y: -1.0 }, Coord { x: 0.0, y: 1.0 }),
+///     Line::new(Coord { x: 0.5, y: 0.0 }, Coord { x: 1.0, y: 0.0 }),
+///     Line::new(Coord { x: 2.0, y: 2.0 }, Coord { x: 2.0, y: 2.0 }),
+/// ];
+///
+/// assert_eq!(clean_segments(&walls).len(), 5);
+/// ```
+pub fn clean_segments(segments: &[geo::Line<f64>]) -> Vec<geo::Line<f64>> {
+    split_segments(segments)
+        .into_iter()
+        .map(|(segment, _)| segment)
+        .collect()
+}
+
+/// Splits the segments as [`clean_segments`] does, along with the index of the segment each piece comes from.
+pub(crate) fn split_segments(segments: &[geo::Line<f64>]) -> Vec<(geo::Line<f64>, usize)> {
+    // points where each segment is split, along with their position along it
+    let mut splits: Vec<Vec<(f64, geo::Coord<f64>)>> = segments
+        .iter()
+        .map(|segment| vec![(0.0, segment.start), (1.0, segment.end)])
+        .collect();
+
+    // only segments whose abscissa ranges overlap are tested against each other
+    let mut order: Vec<_> = (0..segments.len()).collect();
+    let min_x = |index: &usize| segments[*index].start.x.min(segments[*index].end.x);
+    let max_x = |index: &usize| segments[*index].start.x.max(segments[*index].end.x);
+    order.sort_by(|a, b| min_x(a).total_cmp(&min_x(b)));
+    for (position, i) in order.iter().enumerate() {
+        for j in order[position + 1..]
+            .iter()
+            .take_while(|j| min_x(j) <= max_x(i))
+        {
+            let (a, b) = (segments[*i], segments[*j]);
+            let points = match line_intersection(a, b) {
+                Some(LineIntersection::SinglePoint { intersection, .. }) => vec![intersection],
+                Some(LineIntersection::Collinear { intersection }) => {
+                    vec![intersection.start, intersection.end]
+                }
+                None => continue,
+            };
+            for point in points {
+                splits[*i].push((position_along(&a, &point), point));
+                splits[*j].push((position_along(&b, &point), point));
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut pieces = Vec::new();
+    for (index, mut points) in splits.into_iter().enumerate() {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for pair in points.windows(2) {
+            let piece = geo::Line::new(pair[0].1, pair[1].1);
+            if approx_equal(&piece.start_point(), &piece.end_point()) {
+                continue;
+            }
+            if seen.insert(segment_key(&piece)) {
+                pieces.push((piece, index));
+            }
+        }
+    }
+    pieces
+}
+
+/// Gap closed by [`close_gaps`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClosedGap {
+    /// Endpoints closer than the snapping tolerance were moved to their centroid.
+    Snapped {
+        /// Endpoints before snapping.
+        endpoints: Vec<geo::Coord<f64>>,
+        /// Point they were moved to.
+        to: geo::Coord<f64>,
+    },
+    /// A segment was added from a dangling endpoint to another endpoint or to a segment.
+    Bridged(geo::Line<f64>),
+}
+
+/// Segments whose gaps were closed, along with the gaps that were closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GapClosing {
+    /// Segments after snapping, including the bridges.
+    pub segments: Vec<geo::Line<f64>>,
+    /// Closed gaps, snapped endpoints first.
+    pub closed: Vec<ClosedGap>,
+}
+
+/// Closes the small gaps of a floorplan through which the view would leak.
+///
+/// Endpoints closer than `snap_tolerance` to the first of them by abscissa are first snapped together,
+/// but the ends of a segment are never snapped to each other.
+/// Then dangling endpoints, which no other segment touches, are bridged to the closest dangling endpoint
+/// or else to the closest segment when it is nearer than `bridge_threshold`.
+///
+/// # Example
+///
+/// ```
+/// use geo::{Coord, Line};
+/// use geo_visibility::{close_gaps, ClosedGap};
+///
+/// let walls = [
+///     Line::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 1.0, y: 0.0 }),
+///     Line::new(Coord { x: 1.001, y: 0.0 }, Coord { x: 1.0, y: 1.0 }),
+///     Line::new(Coord { x: 1.0, y: 1.0 }, Coord { x: 0.0, y: 1.1 }),
+///     Line::new(Coord { x: 0.0, y: 0.9 }, Coord { x: 0.0, y: 0.0 }),
+/// ];
+///
+/// let closing = close_gaps(&walls, 0.01, 0.5);
+///
+/// assert_eq!(closing.segments.len(), 5);
+/// assert!(matches!(closing.closed[0], ClosedGap::Snapped { .. }));
+/// assert!(matches!(closing.closed[1], ClosedGap::Bridged(_)));
+/// ```
+pub fn close_gaps(
+    segments: &[geo::Line<f64>],
+    snap_tolerance: f64,
+    bridge_threshold: f64,
+) -> GapClosing {
+    let mut closed = Vec::new();
+
+    // endpoints within the tolerance of a representative are gathered around it, visiting them by abscissa
+    // the ends of a segment, whose indices only differ by their last bit, are never gathered together
+    let mut endpoints: Vec<_> = segments
+        .iter()
+        .flat_map(|segment| vec![segment.start, segment.end])
+        .collect();
+    let mut order: Vec<_> = (0..endpoints.len()).collect();
+    order.sort_by(|a, b| endpoints[*a].x.total_cmp(&endpoints[*b].x));
+    let mut gathered = vec![false; endpoints.len()];
+    let mut clusters = Vec::new();
+    for (position, representative) in order.iter().enumerate() {
+        if gathered[*representative] {
+            continue;
+        }
+        let mut cluster = vec![*representative];
+        for other in order[position + 1..].iter().take_while(|other| {
+            endpoints[**other].x - endpoints[*representative].x <= snap_tolerance
+        }) {
+            if !gathered[*other]
+                && !cluster.contains(&(*other ^ 1))
+                && distance(&endpoints[*representative], &endpoints[*other]) <= snap_tolerance
+            {
+                cluster.push(*other);
+            }
+        }
+        for index in &cluster {
+            gathered[*index] = true;
+        }
+        clusters.push(cluster);
+    }
+    clusters.sort_by_key(|cluster| cluster.iter().min().copied());
+    for cluster in clusters {
+        let mut points: Vec<_> = cluster.iter().map(|index| endpoints[*index]).collect();
+        points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        points.dedup();
+        if points.len() < 2 {
+            continue;
+        }
+        let to = points
+            .iter()
+            .fold(geo::Coord { x: 0.0, y: 0.0 }, |sum, point| sum + *point)
+            / points.len() as f64;
+        for index in &cluster {
+            endpoints[*index] = to;
+        }
+        closed.push(ClosedGap::Snapped {
+            endpoints: points,
+            to,
+        });
+    }
+
+    let mut segments: Vec<_> = endpoints
+        .chunks(2)
+        .map(|pair| geo::Line::new(pair[0], pair[1]))
+        .filter(|segment| segment.start != segment.end)
+        .collect();
+
+    // an endpoint is dangling if it is the end of no other segment and lies on no other segment,
+    // only segments whose abscissa ranges overlap are tested against each other
+    let mut touched = vec![[false; 2]; segments.len()];
+    let mut order: Vec<_> = (0..segments.len()).collect();
+    let min_x = |index: &usize| segments[*index].start.x.min(segments[*index].end.x);
+    let max_x = |index: &usize| segments[*index].start.x.max(segments[*index].end.x);
+    order.sort_by(|a, b| min_x(a).total_cmp(&min_x(b)));
+    for (position, i) in order.iter().enumerate() {
+        for j in order[position + 1..]
+            .iter()
+            .take_while(|j| min_x(j) <= max_x(i) + f64::EPSILON)
+        {
+            for (a, b) in [(*i, *j), (*j, *i)] {
+                for (end, point) in [segments[a].start, segments[a].end].iter().enumerate() {
+                    if approx_equal(&closest_point(&segments[b], point).into(), &(*point).into()) {
+                        touched[a][end] = true;
+                    }
+                }
+            }
+        }
+    }
+    let dangling: Vec<_> = segments
+        .iter()
+        .enumerate()
+        .flat_map(|(index, segment)| vec![(index, 0, segment.start), (index, 1, segment.end)])
+        .filter(|(index, end, _)| !touched[*index][*end])
+        .map(|(index, _, point)| (index, point))
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, (a_segment, a)) in dangling.iter().enumerate() {
+        for (b_segment, b) in &dangling[i + 1..] {
+            let gap = distance(a, b);
+            if a_segment != b_segment && gap <= bridge_threshold {
+                pairs.push((gap, *a, *b));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut bridged = HashSet::new();
+    let mut bridges = Vec::new();
+    for (_, a, b) in pairs {
+        if bridged.contains(&point_key(&a)) || bridged.contains(&point_key(&b)) {
+            continue;
+        }
+        bridged.insert(point_key(&a));
+        bridged.insert(point_key(&b));
+        bridges.push(geo::Line::new(a, b));
+    }
+
+    // endpoints left dangling are bridged to the closest point of another segment
+    for (index, point) in &dangling {
+        if bridged.contains(&point_key(point)) {
+            continue;
+        }
+        let closest = segments
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| other != index)
+            .map(|(_, segment)| closest_point(segment, point))
+            .map(|closest| (distance(point, &closest), closest))
+            .filter(|(gap, _)| *gap <= bridge_threshold)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, closest)) = closest {
+            bridges.push(geo::Line::new(*point, closest));
+        }
+    }
+
+    for bridge in bridges {
+        closed.push(ClosedGap::Bridged(bridge));
+        segments.push(bridge);
+    }
+
+    GapClosing { segments, closed }
+}
+
+fn distance(a: &geo::Coord<f64>, b: &geo::Coord<f64>) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Returns the point of the segment closest to the point.
+fn closest_point(segment: &geo::Line<f64>, point: &geo::Coord<f64>) -> geo::Coord<f64> {
+    let t = position_along(segment, point).clamp(0.0, 1.0);
+    segment.start + segment.delta() * t
+}
+
+fn point_key(point: &geo::Coord<f64>) -> [u64; 2] {
+    [point.x.to_bits(), point.y.to_bits()]
+}
+
+/// Returns the position of a point of the segment, from 0 at its start to 1 at its end.
+fn position_along(segment: &geo::Line<f64>, point: &geo::Coord<f64>) -> f64 {
+    let delta = segment.delta();
+    let length = delta.x * delta.x + delta.y * delta.y;
+    if length == 0.0 {
+        return 0.0;
+    }
+    ((point.x - segment.start.x) * delta.x + (point.y - segment.start.y) * delta.y) / length
+}
+
+/// Returns a key identifying a segment whatever the order of its endpoints.
+pub(crate) fn segment_key(segment: &geo::Line<f64>) -> [u64; 4] {
+    let start = [segment.start.x.to_bits(), segment.start.y.to_bits()];
+    let end = [segment.end.x.to_bits(), segment.end.y.to_bits()];
+    let (a, b) = if start <= end {
+        (start, end)
+    } else {
+        (end, start)
+    };
+    [a[0], a[1], b[0], b[1]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Coord;
+
+    fn line([x1, y1]: [f64; 2], [x2, y2]: [f64; 2]) -> geo::Line<f64> {
+        geo::Line::new(Coord { x: x1, y: y1 }, Coord { x: x2, y: y2 })
+    }
+
+    #[test]
+    fn split_crossing_segments() {
+        let pieces =
+            clean_segments(&[line([-1.0, 0.0], [1.0, 0.0]), line([0.0, -1.0], [0.0, 1.0])]);
+
+        assert_eq!(
+            pieces,
+            vec![
+                line([-1.0, 0.0], [0.0, 0.0]),
+                line([0.0, 0.0], [1.0, 0.0]),
+                line([0.0, -1.0], [0.0, 0.0]),
+                line([0.0, 0.0], [0.0, 1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_t_junctions() {
+        let pieces = clean_segments(&[line([0.0, 0.0], [2.0, 0.0]), line([1.0, 0.0], [1.0, 1.0])]);
+
+        assert_eq!(pieces.len(), 3);
+        assert!(pieces.contains(&line([0.0, 0.0], [1.0, 0.0])));
+        assert!(pieces.contains(&line([1.0, 0.0], [2.0, 0.0])));
+    }
+
+    #[test]
+    fn merge_collinear_overlaps() {
+        let pieces = split_segments(&[
+            line([0.0, 0.0], [2.0, 0.0]),
+            line([3.0, 0.0], [1.0, 0.0]),
+            line([0.0, 0.0], [2.0, 0.0]),
+        ]);
+
+        assert_eq!(
+            pieces,
+            vec![
+                (line([0.0, 0.0], [1.0, 0.0]), 0),
+                (line([1.0, 0.0], [2.0, 0.0]), 0),
+                (line([3.0, 0.0], [2.0, 0.0]), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn snap_close_endpoints() {
+        let closing = close_gaps(
+            &[line([0.0, 0.0], [1.0, 0.0]), line([1.0, 0.002], [1.0, 1.0])],
+            0.01,
+            0.0,
+        );
+
+        assert_eq!(
+            closing.segments,
+            vec![
+                line([0.0, 0.0], [1.0, 0.001]),
+                line([1.0, 0.001], [1.0, 1.0])
+            ]
+        );
+        assert_eq!(
+            closing.closed,
+            vec![ClosedGap::Snapped {
+                endpoints: vec![Coord { x: 1.0, y: 0.0 }, Coord { x: 1.0, y: 0.002 }],
+                to: Coord { x: 1.0, y: 0.001 },
+            }]
+        );
+    }
+
+    #[test]
+    fn bound_snapped_clusters_by_the_tolerance() {
+        let closing = close_gaps(
+            &[
+                line([0.0, 0.0], [1.0, 0.0]),
+                line([1.008, 0.0], [1.008, 1.0]),
+                line([1.016, 0.0], [2.0, 0.0]),
+            ],
+            0.01,
+            0.0,
+        );
+
+        assert_eq!(
+            closing.closed,
+            vec![ClosedGap::Snapped {
+                endpoints: vec![Coord { x: 1.0, y: 0.0 }, Coord { x: 1.008, y: 0.0 }],
+                to: Coord { x: 1.004, y: 0.0 },
+            }]
+        );
+    }
+
+    #[test]
+    fn keep_segments_shorter_than_the_tolerance() {
+        let walls = [line([0.0, 0.0], [0.005, 0.0])];
+
+        let closing = close_gaps(&walls, 0.01, 0.0);
+
+        assert_eq!(closing.segments, walls.to_vec());
+        assert!(closing.closed.is_empty());
+    }
+
+    #[test]
+    fn bridge_dangling_endpoints_to_segments() {
+        let closing = close_gaps(
+            &[line([0.0, 0.0], [2.0, 0.0]), line([1.0, 0.05], [1.0, 1.0])],
+            0.01,
+            0.1,
+        );
+
+        assert_eq!(
+            closing.closed,
+            vec![ClosedGap::Bridged(line([1.0, 0.05], [1.0, 0.0]))]
+        );
+        assert_eq!(closing.segments.len(), 3);
+    }
+
+    #[test]
+    fn stop_the_view_leaking_through_gaps() {
+        use crate::visibility::Visibility;
+        use geo::Area;
+
+        let walls = [
+            line([0.0, 0.0], [4.0, 0.0]),
+            line([4.0, 0.0], [4.0, 4.0]),
+            line([4.0, 4.0], [0.0, 4.0]),
+            line([0.0, 4.0], [0.0, 2.01]),
+            line([0.0, 1.99], [0.0, 0.0]),
+        ];
+        let viewpoint = geo::Point::new(2.0, 2.0);
+
+        let closing = close_gaps(&walls, 0.001, 0.05);
+        let visibility = viewpoint.visibility(closing.segments.as_slice());
+
+        assert_eq!(closing.closed.len(), 1);
+        assert!((visibility.unsigned_area() - 16.0).abs() < 1E-9);
+    }
+
+    #[test]
+    fn remove_zero_length_segments() {
+        assert!(clean_segments(&[line([1.0, 1.0], [1.0, 1.0])]).is_empty());
+    }
+}
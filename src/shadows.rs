@@ -0,0 +1,126 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::Obstacles;
+use crate::visibility::{EdgeSource, VisibilityPolygon};
+use geo::{Distance, Euclidean};
+
+/// Parts of a domain hidden from a viewpoint, along with the obstacles casting them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadows {
+    /// Hidden part of the domain, one polygon per shadow.
+    pub region: geo::MultiPolygon<f64>,
+    /// Indices of the obstacle segments casting each shadow, in the order of the region polygons.
+    ///
+    /// A segment casts a shadow when the view goes past one of its endpoints along the shadow boundary.
+    /// Indices refer to the [`ObstacleSegments`](crate::ObstacleSegments) of the obstacles, in increasing order.
+    /// They are empty for the parts of the domain hidden by nothing but its distance to the viewpoint.
+    pub occluders: Vec<Vec<usize>>,
+}
+
+/// Computes the part of the domain hidden from the viewpoint by the obstacles with the given boolean operations.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon, Area};
+/// use geo_visibility::{shadows, BooleanBackend};
+///
+/// let domain = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+///     interiors: [[(x: 4.0, y: 4.0), (x: 6.0, y: 4.0), (x: 6.0, y: 6.0), (x: 4.0, y: 6.0)]],
+/// );
+///
+/// let shadows = shadows(point!(x: 1.0, y: 5.0), &domain, &domain, BooleanBackend::default());
+///
+/// // the pillar, whose sides are the segments 4 to 7, casts a single shadow
+/// assert_eq!(shadows.region.0.len(), 1);
+/// assert!((shadows.region.unsigned_area() - 20.0).abs() < 1E-2);
+/// assert!(shadows.occluders[0].iter().all(|index| (4..8).contains(index)));
+/// ```
+pub fn shadows<T: Obstacles + ?Sized>(
+    viewpoint: geo::Point<f64>,
+    obstacles: &T,
+    domain: &geo::Polygon<f64>,
+    backend: BooleanBackend,
+) -> Shadows {
+    let visibility = VisibilityPolygon::new(viewpoint, obstacles);
+    let region = backend.difference(domain, &visibility.polygon);
+
+    // windows lie along the boundary of the shadows they let appear
+    let occluders = region
+        .0
+        .iter()
+        .map(|shadow| {
+            let mut occluders: Vec<_> = visibility
+                .lines()
+                .filter_map(|(edge, source)| match source {
+                    EdgeSource::Window(index) => {
+                        let tolerance =
+                            backend.precision() + 1E-9 * edge.start.x.hypot(edge.start.y);
+                        Some(index).filter(|_| Euclidean::distance(&edge, shadow) <= tolerance)
+                    }
+                    _ => None,
+                })
+                .collect();
+            occluders.sort_unstable();
+            occluders.dedup();
+            occluders
+        })
+        .collect();
+
+    Shadows { region, occluders }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{point, polygon};
+
+    #[test]
+    fn attribute_each_shadow_to_its_occluder() {
+        let domain = polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [
+                [(x: 4.0, y: 1.0), (x: 5.0, y: 1.0), (x: 5.0, y: 2.0), (x: 4.0, y: 2.0)],
+                [(x: 4.0, y: 7.0), (x: 5.0, y: 7.0), (x: 5.0, y: 8.0), (x: 4.0, y: 8.0)],
+            ],
+        );
+
+        let shadows = shadows(
+            point!(x: 1.0, y: 5.0),
+            &domain,
+            &domain,
+            BooleanBackend::default(),
+        );
+
+        assert_eq!(shadows.region.0.len(), 2);
+        let mut occluders = shadows.occluders.clone();
+        occluders.sort();
+        assert!(
+            !occluders[0].is_empty() && occluders[0].iter().all(|index| (4..8).contains(index))
+        );
+        assert!(
+            !occluders[1].is_empty() && occluders[1].iter().all(|index| (8..12).contains(index))
+        );
+    }
+
+    #[test]
+    fn cast_no_shadow_in_a_convex_room() {
+        let domain =
+            polygon![(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 4.0)];
+
+        let shadows = shadows(
+            point!(x: 1.0, y: 1.0),
+            &domain,
+            &domain,
+            BooleanBackend::Geo,
+        );
+
+        assert!(shadows.region.0.is_empty());
+        assert!(shadows.occluders.is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::obstacles::Obstacles;
+use crate::visibility_graph::VisibilityGraph;
+
+/// Error returned when no shortest path can be found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShortestPathError {
+    /// The obstacles separate the start from the goal.
+    Unreachable,
+}
+
+impl std::fmt::Display for ShortestPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShortestPathError::Unreachable => write!(f, "goal is not reachable from start"),
+        }
+    }
+}
+
+impl std::error::Error for ShortestPathError {}
+
+/// Computes the Euclidean shortest path from start to goal among obstacles.
+///
+/// The path is found with A* on the visibility graph of the obstacle vertices, the start and the goal.
+/// Start and goal do not need to be obstacle vertices but they are expected to lie in the free space.
+/// Polygon obstacles bound rooms, as in [`VisibilityGraph`]: obstacles standing in an open space are wrapped in
+/// [`Solids`].
+///
+/// [`Solids`]: crate::Solids
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon};
+/// use geo_visibility::shortest_path;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let path = shortest_path(point!(x: 3.5, y: 0.5), point!(x: 0.5, y: 3.5), &domain).unwrap();
+///
+/// assert_eq!(path.0.len(), 3);
+/// ```
+pub fn shortest_path<T: Obstacles + ?Sized>(
+    start: geo::Point<f64>,
+    goal: geo::Point<f64>,
+    obstacles: &T,
+) -> Result<geo::LineString<f64>, ShortestPathError> {
+    let graph = VisibilityGraph::with_points(obstacles, &[start, goal]);
+    let (from, to) = match (graph.node(&start), graph.node(&goal)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(ShortestPathError::Unreachable),
+    };
+    let path = graph
+        .shortest_path(from, to)
+        .ok_or(ShortestPathError::Unreachable)?;
+
+    let mut coords: Vec<geo::Coord<f64>> = path.iter().map(|node| graph.nodes()[*node].0).collect();
+    if coords.len() == 1 {
+        coords.push(coords[0]);
+    }
+    Ok(geo::LineString(coords))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solids;
+    use approx::assert_relative_eq;
+    use geo::{point, polygon, Euclidean, Length};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn go_around_a_hole() {
+        let path =
+            shortest_path(point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0), &domain()).unwrap();
+
+        assert_eq!(path.0.len(), 4);
+        assert_eq!(path.0.first(), Some(&geo::Coord { x: 1.0, y: 5.0 }));
+        assert_eq!(path.0.last(), Some(&geo::Coord { x: 9.0, y: 5.0 }));
+        assert_relative_eq!(
+            path.length::<Euclidean>(),
+            4.0 + 2.0 * 5.0_f64.sqrt(),
+            epsilon = 1E-9
+        );
+    }
+
+    #[test]
+    fn go_straight_when_goal_is_visible() {
+        let path =
+            shortest_path(point!(x: 1.0, y: 1.0), point!(x: 9.0, y: 2.0), &domain()).unwrap();
+
+        assert_eq!(path.0.len(), 2);
+    }
+
+    #[test]
+    fn start_on_an_obstacle_vertex() {
+        let path =
+            shortest_path(point!(x: 3.0, y: 4.0), point!(x: 7.0, y: 6.0), &domain()).unwrap();
+
+        assert_eq!(path.0.len(), 3);
+        assert_relative_eq!(path.length::<Euclidean>(), 6.0, epsilon = 1E-9);
+    }
+
+    #[test]
+    fn fail_when_goal_is_unreachable() {
+        assert_eq!(
+            shortest_path(point!(x: 1.0, y: 5.0), point!(x: 5.0, y: 5.0), &domain()),
+            Err(ShortestPathError::Unreachable)
+        );
+
+        let rooms = geo::MultiPolygon(vec![
+            polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0)],
+            polygon![(x: 2.0, y: 0.0), (x: 3.0, y: 0.0), (x: 3.0, y: 1.0), (x: 2.0, y: 1.0)],
+        ]);
+        assert_eq!(
+            shortest_path(point!(x: 0.5, y: 0.5), point!(x: 2.5, y: 0.5), &rooms),
+            Err(ShortestPathError::Unreachable)
+        );
+    }
+
+    #[test]
+    fn go_around_solids_in_open_space() {
+        let buildings = geo::MultiPolygon(vec![
+            polygon![(x: 2.0, y: -1.0), (x: 4.0, y: -1.0), (x: 4.0, y: 1.0), (x: 2.0, y: 1.0)],
+            polygon![(x: 6.0, y: -1.0), (x: 8.0, y: -1.0), (x: 8.0, y: 1.0), (x: 6.0, y: 1.0)],
+        ]);
+        let (start, goal) = (point!(x: 0.0, y: 0.0), point!(x: 10.0, y: 0.0));
+
+        // on their own, the footprints are rooms the path cannot enter
+        assert_eq!(
+            shortest_path(start, goal, &buildings),
+            Err(ShortestPathError::Unreachable)
+        );
+
+        let path = shortest_path(start, goal, &Solids(buildings)).unwrap();
+
+        assert_relative_eq!(
+            path.length::<Euclidean>(),
+            6.0 + 2.0 * 5.0_f64.sqrt(),
+            epsilon = 1E-9
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::Obstacles;
+use crate::visibility::Visibility;
+use crate::visibility_graph::{ring_wedges, VisibilityGraph};
+use geo::{BoundingRect, Distance, Euclidean, Intersects};
+
+/// Number of points used to approximate the boundary between two regions.
+const BISECTOR_SAMPLES: usize = 128;
+
+/// Region of the domain whose shortest paths from the source last bend at the same vertex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortestPathRegion {
+    /// Last vertex of the shortest paths towards the region, the source itself for the visible region.
+    pub vertex: geo::Point<f64>,
+    /// Geodesic distance from the source to the vertex.
+    pub distance: f64,
+    /// Vertex preceding this one on the shortest path from the source, `None` for the source.
+    pub predecessor: Option<geo::Point<f64>>,
+    /// Points of the domain reached through the vertex.
+    pub region: geo::MultiPolygon<f64>,
+}
+
+/// Shortest path map of a polygonal domain with holes.
+///
+/// The domain is partitioned into regions labelled with the vertex through which the shortest path from the source
+/// reaches them last. A region is the part of its vertex visibility polygon not reached more quickly through
+/// another vertex. Those visibility polygons are computed from the source and from the reflex vertices of the domain.
+/// The boundary between two regions is a hyperbola arc which is approximated by a polyline.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon};
+/// use geo_visibility::ShortestPathMap;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let map = ShortestPathMap::new(point!(x: 3.5, y: 0.5), &domain);
+///
+/// assert_eq!(map.regions().len(), 2);
+/// assert_eq!(map.distance(&point!(x: 0.5, y: 0.5)), Some(3.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortestPathMap {
+    domain: geo::Polygon<f64>,
+    regions: Vec<ShortestPathRegion>,
+    visibilities: Vec<geo::MultiPolygon<f64>>,
+}
+
+impl ShortestPathMap {
+    /// Computes the shortest path map of the domain from the source.
+    ///
+    /// Parts of the domain the source cannot reach are left out of every region.
+    pub fn new(source: geo::Point<f64>, domain: &geo::Polygon<f64>) -> Self {
+        Self::with_backend(source, domain, BooleanBackend::default())
+    }
+
+    /// Computes the shortest path map of the domain from the source with the given boolean operations.
+    pub fn with_backend(
+        source: geo::Point<f64>,
+        domain: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> Self {
+        let graph = VisibilityGraph::with_points(domain, &[source]);
+        let root = match graph.node(&source) {
+            Some(root) => root,
+            None => {
+                return Self {
+                    domain: domain.clone(),
+                    regions: Vec::new(),
+                    visibilities: Vec::new(),
+                }
+            }
+        };
+        let (distances, previous) = graph.shortest_path_tree(root);
+
+        // viewpoints are moved slightly into the free space so that the walls meeting at them still block the view
+        let offset = domain
+            .bounding_rect()
+            .map_or(0.0, |rect| 1E-9 * rect.width().max(rect.height()));
+        let mut vertices = vec![(root, source)];
+        for wedge in domain.rings().iter().flat_map(ring_wedges) {
+            if !wedge.is_reflex() {
+                continue;
+            }
+            if let Some(node) = graph.node(&wedge.vertex) {
+                if node != root && distances[node].is_finite() {
+                    vertices.push((node, wedge.vertex + wedge.free_direction() * offset));
+                }
+            }
+        }
+
+        let visibilities: Vec<_> = vertices
+            .iter()
+            .map(|(_, viewpoint)| backend.intersection(&viewpoint.visibility(domain), domain))
+            .collect();
+
+        let bounds = domain
+            .bounding_rect()
+            .unwrap_or_else(|| geo::Rect::new(source.0, source.0));
+        let regions = vertices
+            .iter()
+            .enumerate()
+            .map(|(i, (node, _))| {
+                let vertex = graph.nodes()[*node];
+                let mut region = visibilities[i].clone();
+                for (j, (other, _)) in vertices.iter().enumerate() {
+                    if i == j || region.0.is_empty() {
+                        continue;
+                    }
+                    let closer = closer_region(
+                        (vertex, distances[*node]),
+                        (graph.nodes()[*other], distances[*other]),
+                        &bounds,
+                    );
+                    if let Some(closer) = closer {
+                        let shortcut = backend.intersection(&visibilities[j], &closer);
+                        region = backend.difference(&region, &shortcut);
+                    }
+                }
+                ShortestPathRegion {
+                    vertex,
+                    distance: distances[*node],
+                    predecessor: previous[*node].map(|node| graph.nodes()[node]),
+                    region,
+                }
+            })
+            .collect();
+
+        Self {
+            domain: domain.clone(),
+            regions,
+            visibilities,
+        }
+    }
+
+    /// Returns the regions of the map, the one visible from the source first.
+    pub fn regions(&self) -> &[ShortestPathRegion] {
+        &self.regions
+    }
+
+    /// Returns the geodesic distance from the source to the point.
+    ///
+    /// Returns `None` if the point is outside the domain or not reachable.
+    pub fn distance(&self, point: &geo::Point<f64>) -> Option<f64> {
+        self.closest_region(point).map(|(_, distance)| distance)
+    }
+
+    /// Returns the last vertex of the shortest path from the source to the point.
+    ///
+    /// Returns `None` if the point is outside the domain or not reachable.
+    pub fn last_vertex(&self, point: &geo::Point<f64>) -> Option<geo::Point<f64>> {
+        self.closest_region(point).map(|(region, _)| region.vertex)
+    }
+
+    fn closest_region(&self, point: &geo::Point<f64>) -> Option<(&ShortestPathRegion, f64)> {
+        if !self.domain.intersects(point) {
+            return None;
+        }
+        self.regions
+            .iter()
+            .zip(&self.visibilities)
+            .filter(|(_, visibility)| visibility.intersects(point))
+            .map(|(region, _)| {
+                (
+                    region,
+                    region.distance + Euclidean::distance(&region.vertex, point),
+                )
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+/// Returns the part of the bounds reached more quickly from the second weighted site than from the first one.
+///
+/// Its boundary is the hyperbola branch where `|px| - |qx| = dq - dp`, sampled in the frame centred between the sites.
+fn closer_region(
+    (p, dp): (geo::Point<f64>, f64),
+    (q, dq): (geo::Point<f64>, f64),
+    bounds: &geo::Rect<f64>,
+) -> Option<geo::Polygon<f64>> {
+    let gap = dq - dp;
+    let focal = Euclidean::distance(&p, &q) / 2.0;
+    if gap >= 2.0 * focal {
+        return None;
+    }
+    let extent = 2.0 * (bounds.width() + bounds.height() + Euclidean::distance(&p, &q))
+        + Euclidean::distance(&p, &geo::Point::from(bounds.center()));
+    if gap <= -2.0 * focal {
+        return Some(geo::Polygon::from(geo::Rect::new(
+            geo::Coord {
+                x: bounds.min().x - extent,
+                y: bounds.min().y - extent,
+            },
+            geo::Coord {
+                x: bounds.max().x + extent,
+                y: bounds.max().y + extent,
+            },
+        )));
+    }
+
+    let center = (p + q) / 2.0;
+    let axis = (q - p) / (2.0 * focal);
+    let normal = geo::Point::new(-axis.y(), axis.x());
+    let a = gap / 2.0;
+    let b = (focal * focal - a * a).sqrt();
+    let limit = (extent / b).asinh();
+
+    let mut points: Vec<_> = (0..=BISECTOR_SAMPLES)
+        .map(|i| {
+            let t = limit * (2.0 * i as f64 / BISECTOR_SAMPLES as f64 - 1.0);
+            (a * t.cosh(), b * t.sinh())
+        })
+        .collect();
+    let far = points[0].0.abs() + extent;
+    points.push((far, b * limit.sinh()));
+    points.push((far, -b * limit.sinh()));
+
+    Some(geo::Polygon::new(
+        points
+            .into_iter()
+            .map(|(x, y)| center + axis * x + normal * y)
+            .collect(),
+        vec![],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::{point, polygon, Area};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn evaluate_geodesic_distance() {
+        let map = ShortestPathMap::new(point!(x: 1.0, y: 5.0), &domain());
+
+        assert_relative_eq!(map.distance(&point!(x: 1.0, y: 5.0)).unwrap(), 0.0);
+        assert_relative_eq!(map.distance(&point!(x: 1.0, y: 1.0)).unwrap(), 4.0);
+        assert_relative_eq!(
+            map.distance(&point!(x: 9.0, y: 4.5)).unwrap(),
+            5.0_f64.sqrt() + 4.0 + 4.25_f64.sqrt(),
+            epsilon = 1E-6
+        );
+        assert_eq!(map.distance(&point!(x: 5.0, y: 5.0)), None);
+        assert_eq!(map.distance(&point!(x: 11.0, y: 5.0)), None);
+    }
+
+    #[test]
+    fn label_points_with_their_last_vertex() {
+        let map = ShortestPathMap::new(point!(x: 1.0, y: 5.0), &domain());
+
+        assert_eq!(
+            map.last_vertex(&point!(x: 1.0, y: 1.0)),
+            Some(point!(x: 1.0, y: 5.0))
+        );
+        assert_eq!(
+            map.last_vertex(&point!(x: 9.0, y: 4.5)),
+            Some(point!(x: 7.0, y: 4.0))
+        );
+        assert_eq!(
+            map.last_vertex(&point!(x: 9.0, y: 5.5)),
+            Some(point!(x: 7.0, y: 6.0))
+        );
+    }
+
+    #[test]
+    fn leave_out_unreachable_parts() {
+        let map = ShortestPathMap::new(point!(x: 5.0, y: 5.0), &domain());
+
+        assert_eq!(map.distance(&point!(x: 1.0, y: 1.0)), None);
+        assert_eq!(map.last_vertex(&point!(x: 9.0, y: 4.5)), None);
+    }
+
+    #[test]
+    fn partition_the_domain() {
+        let map = ShortestPathMap::new(point!(x: 1.0, y: 5.0), &domain());
+
+        assert_eq!(map.regions().len(), 5);
+        assert_eq!(map.regions()[0].vertex, point!(x: 1.0, y: 5.0));
+        assert_eq!(map.regions()[0].predecessor, None);
+        let area: f64 = map
+            .regions()
+            .iter()
+            .map(|region| region.region.unsigned_area())
+            .sum();
+        assert_relative_eq!(area, domain().unsigned_area(), epsilon = 1E-1);
+
+        let region = map
+            .regions()
+            .iter()
+            .find(|region| region.vertex == point!(x: 7.0, y: 4.0))
+            .unwrap();
+        assert_eq!(region.predecessor, Some(point!(x: 3.0, y: 4.0)));
+        assert!(region.region.intersects(&point!(x: 9.0, y: 4.5)));
+        assert!(!region.region.intersects(&point!(x: 9.0, y: 5.5)));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::utils::cross;
+use geo::{Area, BoundingRect};
+
+/// Polygon star-shaped with respect to a centre, such as a visibility polygon from its viewpoint.
+///
+/// Edges are sorted by angle around the centre so that testing whether a point is inside only needs a binary search
+/// for the edge in the direction of the point.
+#[derive(Debug, Clone)]
+pub(crate) struct StarPolygon {
+    center: geo::Point<f64>,
+    bounds: Option<geo::Rect<f64>>,
+    // edges going counterclockwise around the centre, sorted by the angle of their start
+    edges: Vec<(f64, geo::Point<f64>, geo::Point<f64>)>,
+}
+
+impl StarPolygon {
+    pub(crate) fn new(center: geo::Point<f64>, polygon: &geo::Polygon<f64>) -> Self {
+        let clockwise = polygon.signed_area() < 0.0;
+        let mut edges: Vec<_> = polygon
+            .exterior()
+            .lines()
+            .filter(|line| cross(line.start_point() - center, line.end_point() - center) != 0.0)
+            .map(|line| {
+                let (start, end) = if clockwise {
+                    (line.end_point(), line.start_point())
+                } else {
+                    (line.start_point(), line.end_point())
+                };
+                let direction = start - center;
+                (direction.y().atan2(direction.x()), start, end)
+            })
+            .collect();
+        edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self {
+            center,
+            bounds: polygon.bounding_rect(),
+            edges,
+        }
+    }
+
+    pub(crate) fn bounds(&self) -> Option<geo::Rect<f64>> {
+        self.bounds
+    }
+
+    /// Returns `true` if the point is inside the polygon or on its boundary.
+    pub(crate) fn contains(&self, point: &geo::Point<f64>) -> bool {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+        if point.x() < bounds.min().x
+            || point.x() > bounds.max().x
+            || point.y() < bounds.min().y
+            || point.y() > bounds.max().y
+        {
+            return false;
+        }
+
+        let direction = *point - self.center;
+        let angle = direction.y().atan2(direction.x());
+        // the edge seen in that direction is the last one starting before it, wrapping around
+        let index = self.edges.partition_point(|(start, _, _)| *start <= angle);
+        let index = (index + self.edges.len() - 1) % self.edges.len().max(1);
+        let (_, start, end) = match self.edges.get(index) {
+            Some(edge) => *edge,
+            None => return false,
+        };
+        if in_front(point, start, end) {
+            return true;
+        }
+        // a point along a window is in front of the edge ending at the window
+        let tolerance = 1E-9
+            * direction.x().hypot(direction.y())
+            * (start - self.center).x().hypot((start - self.center).y());
+        if cross(start - self.center, direction).abs() <= tolerance {
+            let (_, start, end) = self.edges[(index + self.edges.len() - 1) % self.edges.len()];
+            return in_front(point, start, end);
+        }
+        false
+    }
+}
+
+/// Returns `true` if the point is on the left of the edge or on it.
+fn in_front(point: &geo::Point<f64>, start: geo::Point<f64>, end: geo::Point<f64>) -> bool {
+    let edge = end - start;
+    let offset = *point - start;
+    let tolerance = 1E-9 * (edge.x().hypot(edge.y()) * offset.x().hypot(offset.y()));
+    cross(edge, offset) >= -tolerance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visibility::Visibility;
+    use geo::{point, polygon, Contains};
+
+    #[test]
+    fn agree_with_point_in_polygon() {
+        let domain = polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        );
+        let viewpoint = point!(x: 1.0, y: 5.0);
+        let visibility = viewpoint.visibility(&domain);
+        let star = StarPolygon::new(viewpoint, &visibility);
+
+        for i in 0..40 {
+            for j in 0..40 {
+                let point = point!(x: i as f64 * 0.25 + 0.1, y: j as f64 * 0.25 + 0.1);
+                assert_eq!(star.contains(&point), visibility.contains(&point));
+            }
+        }
+        assert!(star.contains(&point!(x: 10.0, y: 0.5)));
+        assert!(!star.contains(&point!(x: 11.0, y: 5.0)));
+    }
+}
@@ -9,6 +9,26 @@ pub fn approx_equal(a: &geo::Point<f64>, b: &geo::Point<f64>) -> bool {
     abs_diff_eq!(Euclidean::distance(a, b), 0.0)
 }
 
+/// Maps every index below `count`, in parallel with the `rayon` feature.
+#[cfg(feature = "rayon")]
+pub fn map_indices<R, F>(count: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync + Send,
+{
+    use rayon::prelude::*;
+    (0..count).into_par_iter().map(f).collect()
+}
+
+/// Maps every index below `count`, in parallel with the `rayon` feature.
+#[cfg(not(feature = "rayon"))]
+pub fn map_indices<R, F>(count: usize, f: F) -> Vec<R>
+where
+    F: Fn(usize) -> R,
+{
+    (0..count).map(f).collect()
+}
+
 #[cfg(test)]
 #[allow(clippy::float_cmp)]
 mod tests {
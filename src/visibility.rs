@@ -1,194 +1,774 @@
-use crate::angle_comparator::AngleComparator;
+use crate::boolean::BooleanBackend;
 use crate::comparable_line::ComparableLine;
+use crate::obstacles::{ObstacleSegments, Obstacles, Solids, Walls};
 use crate::orientation::Orientation;
 use crate::ray::Ray;
+use crate::segment_soup::segment_key;
 use crate::utils::approx_equal;
 use crate::visibility_event::{VisibilityEvent, VisibilityEventType};
+use crate::visibility_graph::Wedge;
 use approx::*;
-use geo_clipper::Clipper;
+use geo::kernels::{Kernel, RobustKernel};
+use geo::Area;
 use log::warn;
-use std::collections::BTreeSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
 
+/// Viewers which can compute the region they see amongst obstacles.
 pub trait Visibility<T: ?Sized> {
-    fn visibility(&self, obstacles: &T) -> geo::Polygon<f64>;
+    /// Region seen by the viewer, a polygon for a point viewer.
+    type Output;
+
+    fn visibility(&self, obstacles: &T) -> Self::Output;
 }
 
 impl Visibility<geo::Polygon<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
     fn visibility(&self, obstacles: &geo::Polygon<f64>) -> geo::Polygon<f64> {
-        let segments: Vec<_> = obstacles
-            .exterior()
-            .lines()
-            .chain(
-                obstacles
-                    .interiors()
-                    .iter()
-                    .flat_map(|interior| interior.lines()),
-            )
-            .collect();
-        self.visibility(segments.as_slice())
+        VisibilityPolygon::new(*self, obstacles).polygon
     }
 }
 
 impl Visibility<geo::MultiPolygon<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
     fn visibility(&self, obstacles: &geo::MultiPolygon<f64>) -> geo::Polygon<f64> {
-        let segments: Vec<_> = obstacles
-            .0
-            .iter()
-            .flat_map(|obstacle| {
-                obstacle
-                    .exterior()
-                    .lines()
-                    .chain(obstacles.0.iter().flat_map(|obstacle| {
-                        obstacle
-                            .interiors()
-                            .iter()
-                            .flat_map(|interior| interior.lines())
-                    }))
-            })
-            .collect();
-        self.visibility(segments.as_slice())
+        VisibilityPolygon::new(*self, obstacles).polygon
     }
 }
 
-impl Visibility<[geo::Line<f64>]> for geo::Point<f64> {
-    fn visibility(&self, obstacles: &[geo::Line<f64>]) -> geo::Polygon<f64> {
-        let mut state = BTreeSet::new();
-        let mut events = Vec::with_capacity(obstacles.len() * 2 + 1);
+impl Visibility<geo::Line<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
 
-        for segment in obstacles {
-            let a = geo::Point::from(segment.start);
-            let b = geo::Point::from(segment.end);
+    fn visibility(&self, obstacles: &geo::Line<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
 
-            // Sort line segment endpoints and add them as events
-            // Skip line segments collinear with the point
-            match Orientation::from(*self, a, b) {
-                Orientation::Collinear => {
-                    continue;
-                }
-                Orientation::RightTurn => {
-                    events.push(VisibilityEvent::start(segment));
-                    events.push(VisibilityEvent::end(&geo::Line::new(
-                        segment.end,
-                        segment.start,
-                    )));
-                }
-                Orientation::LeftTurn => {
-                    events.push(VisibilityEvent::start(&geo::Line::new(
-                        segment.end,
-                        segment.start,
-                    )));
-                    events.push(VisibilityEvent::end(segment));
-                }
-            }
+impl Visibility<geo::LineString<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
 
-            // Initialize state by adding line segments that are intersected by vertical ray from the point
-            let (a, b) = if a.x() > b.x() { (b, a) } else { (a, b) };
-            let abp = Orientation::from(a, b, *self);
+    fn visibility(&self, obstacles: &geo::LineString<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
 
-            if (abs_diff_eq!(b.x(), self.x()) || (a.x() < self.x() && self.x() < b.x()))
-                && abp == Orientation::RightTurn
-            {
-                state.insert(ComparableLine::new(*self, *segment));
-            }
+impl Visibility<geo::MultiLineString<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &geo::MultiLineString<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl Visibility<geo::Rect<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &geo::Rect<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl Visibility<geo::Triangle<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &geo::Triangle<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl Visibility<geo::Geometry<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &geo::Geometry<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl Visibility<geo::GeometryCollection<f64>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &geo::GeometryCollection<f64>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl<T: Obstacles> Visibility<[T]> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &[T]) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl<T: Obstacles> Visibility<Vec<T>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &Vec<T>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl<I> Visibility<Walls<I>> for geo::Point<f64>
+where
+    I: IntoIterator<Item = geo::Line<f64>> + Clone,
+{
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &Walls<I>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+impl<T: Obstacles> Visibility<Solids<T>> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &Solids<T>) -> geo::Polygon<f64> {
+        VisibilityPolygon::new(*self, obstacles).polygon
+    }
+}
+
+/// Segments extracted beforehand are shared by the viewpoints, see [`VisibilityPolygon::among`].
+impl Visibility<ObstacleSegments> for geo::Point<f64> {
+    type Output = geo::Polygon<f64>;
+
+    fn visibility(&self, obstacles: &ObstacleSegments) -> geo::Polygon<f64> {
+        VisibilityPolygon::among(*self, obstacles).polygon
+    }
+}
+
+/// Side of the walls a viewpoint lying on them looks at.
+///
+/// Sides are given relative to the direction of the walls, that is the direction of the segments,
+/// or that of the rings for obstacles bounding a free space, whose left side is the free space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountSide {
+    /// The viewpoint looks at the left of the walls.
+    Left,
+    /// The viewpoint looks at the right of the walls.
+    Right,
+}
+
+/// Whether light passes through a vertex joining two walls when a ray from the viewpoint grazes it.
+///
+/// The policy only matters when the walls lie on both sides of the ray,
+/// otherwise the ray goes past the vertex with either policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CornerPolicy {
+    /// Walls joined at a vertex block the ray going through it.
+    #[default]
+    Closed,
+    /// The ray goes through the vertex, leaving a spike of null width in the visibility polygon.
+    Open,
+}
+
+/// Origin of an edge of a visibility polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeSource {
+    /// The edge lies along the obstacle segment with the given index.
+    Obstacle(usize),
+    /// The edge is a window: the view goes past an endpoint of the obstacle segment with the given index.
+    Window(usize),
+    /// No obstacle bounds the view along the edge.
+    Open,
+}
+
+/// Visibility polygon of a viewpoint along with the origin of each of its edges.
+///
+/// # Example
+///
+/// ```
+/// use geo::{Coord, Line};
+/// use geo_visibility::{EdgeSource, VisibilityPolygon};
+///
+/// let lines = vec![
+///     Line::new(Coord { x: -2.0, y: -2.0 }, Coord { x: 2.0, y: -2.0 }),
+///     Line::new(Coord { x: 2.0, y: -2.0 }, Coord { x: 2.0, y: 2.0 }),
+///     Line::new(Coord { x: 2.0, y: 2.0 }, Coord { x: -2.0, y: 2.0 }),
+///     Line::new(Coord { x: -2.0, y: 2.0 }, Coord { x: -2.0, y: -2.0 }),
+///     Line::new(Coord { x: 1.0, y: 1.0 }, Coord { x: 1.0, y: -1.0 }),
+/// ];
+///
+/// let visibility = VisibilityPolygon::new(geo::Point::new(0.0, 0.0), lines.as_slice());
+///
+/// let windows = visibility
+///     .edges
+///     .iter()
+///     .filter(|edge| matches!(edge, EdgeSource::Window(4)))
+///     .count();
+/// assert_eq!(windows, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibilityPolygon {
+    /// Point from which the polygon is seen.
+    pub viewpoint: geo::Point<f64>,
+    /// Visibility polygon, as returned by [`Visibility::visibility`].
+    pub polygon: geo::Polygon<f64>,
+    /// Origin of each edge of the polygon exterior, the edge `i` going from the vertex `i` to the next one.
+    ///
+    /// Obstacle segments are indexed in the order given by [`ObstacleSegments`],
+    /// whose origins lead back to the edges of the obstacles.
+    ///
+    /// [`ObstacleSegments`]: crate::ObstacleSegments
+    pub edges: Vec<EdgeSource>,
+}
+
+impl VisibilityPolygon {
+    /// Computes the visibility polygon of the viewpoint amongst the obstacles.
+    ///
+    /// A viewpoint lying on the walls looks at their left side, see [`VisibilityPolygon::mounted`].
+    pub fn new<T: Obstacles + ?Sized>(viewpoint: geo::Point<f64>, obstacles: &T) -> Self {
+        Self::mounted(viewpoint, obstacles, MountSide::Left)
+    }
+
+    /// Computes the visibility polygon of the viewpoint amongst the obstacles with the given corner policy.
+    ///
+    /// [`VisibilityPolygon::new`] closes the corners.
+    pub fn with_corner_policy<T: Obstacles + ?Sized>(
+        viewpoint: geo::Point<f64>,
+        obstacles: &T,
+        corners: CornerPolicy,
+    ) -> Self {
+        compute(viewpoint, obstacles, MountSide::Left, corners)
+    }
+
+    /// Computes the visibility polygon of a viewpoint which may be mounted on the walls.
+    ///
+    /// A viewpoint inside a wall sees the open half-plane on the given side of it.
+    /// A viewpoint at a vertex joining walls sees the wedge between them on the given side,
+    /// the first wall being considered to go towards the vertex and the second to leave it when they are not chained.
+    /// A viewpoint at the free end of a wall sees around it.
+    /// The view is computed from a point moved away from the walls by a billionth of the obstacles extent.
+    pub fn mounted<T: Obstacles + ?Sized>(
+        viewpoint: geo::Point<f64>,
+        obstacles: &T,
+        side: MountSide,
+    ) -> Self {
+        compute(viewpoint, obstacles, side, CornerPolicy::default())
+    }
+
+    /// Computes the visibility polygon of the viewpoint amongst obstacles whose segments were extracted beforehand.
+    ///
+    /// This gives the same polygon as [`VisibilityPolygon::new`] without extracting the segments again,
+    /// which saves time when many viewpoints look at the same obstacles.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use geo::{polygon, Area};
+    /// use geo_visibility::{ObstacleSegments, VisibilityPolygon};
+    ///
+    /// let room = polygon![(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 4.0)];
+    /// let segments = ObstacleSegments::new(&room);
+    ///
+    /// for x in [1.0, 2.0, 3.0] {
+    ///     let visibility = VisibilityPolygon::among(geo::Point::new(x, 1.0), &segments);
+    ///     assert!((visibility.polygon.unsigned_area() - 16.0).abs() < 1E-9);
+    /// }
+    /// ```
+    pub fn among(viewpoint: geo::Point<f64>, segments: &ObstacleSegments) -> Self {
+        look(
+            viewpoint,
+            segments,
+            MountSide::Left,
+            CornerPolicy::default(),
+        )
+    }
+
+    /// Returns the edges of the polygon exterior along with their origin.
+    pub fn lines(&self) -> impl Iterator<Item = (geo::Line<f64>, EdgeSource)> + '_ {
+        self.polygon
+            .exterior()
+            .lines()
+            .zip(self.edges.iter().copied())
+    }
+}
+
+/// Computes the visibility polygon of a viewpoint which may be mounted on the walls with the given corner policy.
+fn compute<T: Obstacles + ?Sized>(
+    viewpoint: geo::Point<f64>,
+    obstacles: &T,
+    side: MountSide,
+    corners: CornerPolicy,
+) -> VisibilityPolygon {
+    let segments = ObstacleSegments::new(obstacles);
+    look(viewpoint, &segments, side, corners)
+}
+
+fn look(
+    viewpoint: geo::Point<f64>,
+    segments: &ObstacleSegments,
+    side: MountSide,
+    corners: CornerPolicy,
+) -> VisibilityPolygon {
+    let eye = mounted_viewpoint(&viewpoint, &segments.walls, side).unwrap_or(viewpoint);
+    let mut visibility = sweep(&eye, &segments.pieces, corners);
+    visibility.viewpoint = viewpoint;
+    visibility
+}
+
+/// Returns the point from which a viewpoint lying on the walls looks at them, `None` if it lies on no wall.
+fn mounted_viewpoint(
+    viewpoint: &geo::Point<f64>,
+    walls: &[geo::Line<f64>],
+    side: MountSide,
+) -> Option<geo::Point<f64>> {
+    let incident: Vec<_> = walls
+        .iter()
+        .filter(|wall| wall.start != wall.end)
+        .filter(|wall| approx_equal(&closest_point(wall, viewpoint), viewpoint))
+        .collect();
+    if incident.is_empty() {
+        return None;
+    }
+
+    // walls ending at the viewpoint come towards it, those starting at it leave it
+    let mut towards = Vec::new();
+    let mut leaving = Vec::new();
+    for wall in &incident {
+        if approx_equal(&wall.end_point(), viewpoint) {
+            towards.push(wall.start_point());
+        } else if approx_equal(&wall.start_point(), viewpoint) {
+            leaving.push(wall.end_point());
         }
+    }
+    let ends: Vec<_> = towards.iter().chain(&leaving).copied().collect();
+    let (previous, next) = match (towards.first(), leaving.first()) {
+        (Some(previous), Some(next)) => (Some(*previous), Some(*next)),
+        _ if ends.len() >= 2 => (Some(ends[0]), Some(ends[1])),
+        _ => (None, None),
+    };
 
-        // sort events by angle
-        sort_events_by_angle(self, &mut events);
+    let direction = match (previous, next) {
+        (Some(previous), Some(next)) => Wedge {
+            vertex: *viewpoint,
+            previous,
+            next,
+        }
+        .free_direction(),
+        _ if ends.len() == 1 => {
+            // at the free end of a wall, the view goes around it
+            let away = *viewpoint - ends[0];
+            return Some(*viewpoint + away / away.x().hypot(away.y()) * offset(walls));
+        }
+        _ => {
+            let delta = incident[0].delta();
+            geo::Point::new(-delta.y, delta.x) / delta.x.hypot(delta.y)
+        }
+    };
+    let direction = match side {
+        MountSide::Left => direction,
+        MountSide::Right => -direction,
+    };
+    Some(*viewpoint + direction * offset(walls))
+}
 
-        // find the visibility polygon
-        let mut vertices = Vec::new();
-        for event in events {
-            let segment = ComparableLine::new(*self, event.segment);
+/// Returns the distance by which mounted viewpoints are moved away from the walls.
+fn offset(walls: &[geo::Line<f64>]) -> f64 {
+    let extent = walls
+        .iter()
+        .flat_map(|wall| [wall.start, wall.end])
+        .fold(0.0_f64, |extent, point| {
+            extent.max(point.x.abs()).max(point.y.abs())
+        });
+    1E-9 * extent.max(1.0)
+}
 
-            if event.event_type == VisibilityEventType::EndVertex {
-                state.remove(&segment);
-            }
+/// Returns the point of the segment closest to the point.
+fn closest_point(segment: &geo::Line<f64>, point: &geo::Point<f64>) -> geo::Point<f64> {
+    let delta = segment.delta();
+    let t = ((point.x() - segment.start.x) * delta.x + (point.y() - segment.start.y) * delta.y)
+        / (delta.x * delta.x + delta.y * delta.y);
+    (segment.start + delta * t.clamp(0.0, 1.0)).into()
+}
+
+/// Sweeps the pieces of the obstacle segments, which meet only at their endpoints, around the origin.
+fn sweep(
+    origin: &geo::Point<f64>,
+    pieces: &[(geo::Line<f64>, usize)],
+    corners: CornerPolicy,
+) -> VisibilityPolygon {
+    // pieces remember the obstacle they come from
+    let indices: HashMap<_, _> = pieces
+        .iter()
+        .map(|(segment, index)| (segment_key(segment), *index))
+        .collect();
+    let source = |segment: &geo::Line<f64>, kind: fn(usize) -> EdgeSource| match indices
+        .get(&segment_key(segment))
+    {
+        Some(index) => kind(*index),
+        None => {
+            warn!("edges of the visibility polygon lie along pieces of the obstacles");
+            EdgeSource::Open
+        }
+    };
+    let segments: Vec<_> = pieces.iter().map(|(segment, _)| *segment).collect();
+
+    let (mut state, mut events) = sweep_state(origin, &segments);
+
+    // sort events by angle
+    sort_events_by_angle(origin, &mut events);
+
+    // find the visibility polygon, along with the origin of the edge starting at each vertex
+    // vertices of spikes going through open corners are pinned so that they are not removed as collinear
+    let mut vertices = Vec::new();
+    let mut edges = Vec::new();
+    let mut pinned = Vec::new();
+    let mut sealed = false;
+    for (index, event) in events.iter().enumerate() {
+        let segment = ComparableLine::new(*origin, event.segment);
+        let joined = match event.event_type {
+            VisibilityEventType::EndVertex => joined_segment(&events, index),
+            VisibilityEventType::StartVertex => None,
+        };
+        if index == 0 || !approx_equal(&event.point(), &events[index - 1].point()) {
+            sealed = false;
+        }
 
-            if let Some(first_state) = state.iter().next() {
-                if segment < *first_state {
-                    let ray = Ray::new(geo::Line::new(*self, event.point()));
-                    if let Some(intersection) = ray.intersects(&first_state.line) {
-                        match event.event_type {
-                            VisibilityEventType::StartVertex => {
-                                vertices.push(intersection);
-                                vertices.push(event.point());
-                            }
-                            VisibilityEventType::EndVertex => {
-                                vertices.push(event.point());
-                                vertices.push(intersection);
-                            }
+        if event.event_type == VisibilityEventType::EndVertex {
+            state.remove(&segment);
+        }
+
+        if let Some(first_state) = state.iter().next() {
+            if segment < *first_state {
+                // rays towards points very close to the origin are lengthened so that they are not deemed parallel
+                let direction = event.point() - *origin;
+                let length = direction.x().hypot(direction.y());
+                let ray = Ray::new(geo::Line::new(
+                    *origin,
+                    *origin + direction / length.min(1.0),
+                ));
+                if let (CornerPolicy::Closed, Some(joined)) = (corners, joined) {
+                    // the corner is closed, the view goes on along the joined segment
+                    vertices.push(event.point());
+                    edges.push(source(&joined, EdgeSource::Obstacle));
+                    pinned.push(false);
+                    sealed = true;
+                } else if sealed {
+                    // the segment starts at a closed corner already added
+                } else if let Some(intersection) = ray.intersects(&first_state.line) {
+                    let spike = corners == CornerPolicy::Open
+                        && (joined.is_some()
+                            || events[..index]
+                                .iter()
+                                .rev()
+                                .take_while(|previous| {
+                                    approx_equal(&previous.point(), &event.point())
+                                })
+                                .any(|previous| {
+                                    previous.event_type == VisibilityEventType::EndVertex
+                                }));
+                    match event.event_type {
+                        VisibilityEventType::StartVertex => {
+                            vertices.push(intersection);
+                            edges.push(source(&event.segment, EdgeSource::Window));
+                            vertices.push(event.point());
+                            edges.push(source(&event.segment, EdgeSource::Obstacle));
+                        }
+                        VisibilityEventType::EndVertex => {
+                            vertices.push(event.point());
+                            edges.push(source(&event.segment, EdgeSource::Window));
+                            vertices.push(intersection);
+                            edges.push(source(&first_state.line, EdgeSource::Obstacle));
                         }
-                    } else {
-                        warn!("ray intersects a line segment iff the line segment is in the state");
                     }
+                    pinned.extend([spike, spike]);
+                } else {
+                    warn!("ray intersects a line segment iff the line segment is in the state");
                 }
-            } else {
-                vertices.push(event.point());
             }
+        } else {
+            vertices.push(event.point());
+            edges.push(match event.event_type {
+                VisibilityEventType::StartVertex => source(&event.segment, EdgeSource::Obstacle),
+                VisibilityEventType::EndVertex => EdgeSource::Open,
+            });
+            pinned.push(false);
+        }
 
-            if event.event_type == VisibilityEventType::StartVertex {
-                state.insert(segment);
-            }
+        if event.event_type == VisibilityEventType::StartVertex {
+            state.insert(segment);
         }
+    }
 
-        // remove collinear points
-        let mut top = 0;
-        for it in 0..vertices.len() {
-            let prev = if top == 0 {
-                vertices.len() - 1
+    // remove collinear points, an edge merged with an empty one keeps the origin of the other
+    let mut top = 0;
+    let mut wrapped_edge = None;
+    for it in 0..vertices.len() {
+        let prev = if top == 0 {
+            vertices.len() - 1
+        } else {
+            top - 1
+        };
+        let next = if it + 1 == vertices.len() { 0 } else { it + 1 };
+
+        if Orientation::from(vertices[prev], vertices[it], vertices[next]) != Orientation::Collinear
+            || (pinned[it] && !approx_equal(&vertices[prev], &vertices[it]))
+        {
+            vertices[top] = vertices[it];
+            edges[top] = edges[it];
+            top += 1;
+        } else if approx_equal(&vertices[prev], &vertices[it]) {
+            if top == 0 {
+                wrapped_edge = Some(edges[it]);
             } else {
-                top - 1
-            };
-            let next = if it + 1 == vertices.len() { 0 } else { it + 1 };
-
-            if Orientation::from(vertices[prev], vertices[it], vertices[next])
-                != Orientation::Collinear
-            {
-                vertices[top] = vertices[it];
-                top += 1;
+                edges[top - 1] = edges[it];
             }
         }
-        vertices.truncate(top);
+    }
+    vertices.truncate(top);
+    edges.truncate(top);
+    if let (Some(edge), Some(last)) = (wrapped_edge, edges.last_mut()) {
+        *last = edge;
+    }
 
-        geo::Polygon::new(vertices.into_iter().collect(), Vec::new())
+    VisibilityPolygon {
+        viewpoint: *origin,
+        polygon: geo::Polygon::new(vertices.into_iter().collect(), Vec::new()),
+        edges,
     }
 }
 
+/// Builds the unsorted sweep events of the obstacles around the origin and the initial sweep state.
+pub(crate) fn sweep_state(
+    origin: &geo::Point<f64>,
+    obstacles: &[geo::Line<f64>],
+) -> (BTreeSet<ComparableLine>, Vec<VisibilityEvent>) {
+    let mut state = BTreeSet::new();
+    let mut events = Vec::with_capacity(obstacles.len() * 2 + 1);
+
+    for segment in obstacles {
+        let a = geo::Point::from(segment.start);
+        let b = geo::Point::from(segment.end);
+
+        // Sort line segment endpoints and add them as events
+        // Skip line segments collinear with the point
+        match Orientation::from(*origin, a, b) {
+            Orientation::Collinear => {
+                continue;
+            }
+            Orientation::RightTurn => {
+                events.push(VisibilityEvent::start(segment));
+                events.push(VisibilityEvent::end(&geo::Line::new(
+                    segment.end,
+                    segment.start,
+                )));
+            }
+            Orientation::LeftTurn => {
+                events.push(VisibilityEvent::start(&geo::Line::new(
+                    segment.end,
+                    segment.start,
+                )));
+                events.push(VisibilityEvent::end(segment));
+            }
+        }
+
+        // Initialize state by adding line segments that are intersected by vertical ray from the point
+        let (a, b) = if a.x() > b.x() { (b, a) } else { (a, b) };
+        let abp = Orientation::from(a, b, *origin);
+
+        if (abs_diff_eq!(b.x(), origin.x()) || (a.x() < origin.x() && origin.x() < b.x()))
+            && abp == Orientation::RightTurn
+        {
+            state.insert(ComparableLine::new(*origin, *segment));
+        }
+    }
+
+    (state, events)
+}
+
+/// Sorts the events by angle around the origin with a total order.
+///
+/// Events at the same point, which the pieces meeting there share exactly, are ordered with the end vertices first,
+/// then by the angle and distance of the other endpoint of their segment.
+/// Angles are compared with exact orientation tests so that the order stays transitive.
 fn sort_events_by_angle(origin: &geo::Point<f64>, events: &mut [VisibilityEvent]) {
-    let angle_comparator = AngleComparator { origin: *origin };
+    let rank = |event: &VisibilityEvent| match event.event_type {
+        VisibilityEventType::EndVertex => 0,
+        VisibilityEventType::StartVertex => 1,
+    };
     events.sort_by(|a, b| {
-        if approx_equal(&a.point(), &b.point()) {
-            match (&a.event_type, &b.event_type) {
-                (VisibilityEventType::EndVertex, VisibilityEventType::StartVertex) => {
-                    std::cmp::Ordering::Less
-                }
-                (VisibilityEventType::StartVertex, VisibilityEventType::EndVertex) => {
-                    std::cmp::Ordering::Greater
-                }
-                _ => std::cmp::Ordering::Greater,
-            }
+        if a.point() == b.point() {
+            rank(a)
+                .cmp(&rank(b))
+                .then_with(|| angle_order(origin, &a.segment.end.into(), &b.segment.end.into()))
         } else {
-            angle_comparator.cmp(&a.point(), &b.point())
+            angle_order(origin, &a.point(), &b.point())
         }
     });
 }
 
+/// Compares the angles of the points around the origin like the angle comparator, with exact orientation tests.
+///
+/// The right half-plane comes first, from the top down, then the left one from the bottom up.
+/// Points in the same direction are ordered by distance to the origin.
+fn angle_order(origin: &geo::Point<f64>, a: &geo::Point<f64>, b: &geo::Point<f64>) -> Ordering {
+    let is_left = |point: &geo::Point<f64>| point.x() < origin.x();
+    is_left(a).cmp(&is_left(b)).then_with(|| {
+        match RobustKernel::orient2d(origin.0, a.0, b.0) {
+            geo::kernels::Orientation::Clockwise => Ordering::Less,
+            geo::kernels::Orientation::CounterClockwise => Ordering::Greater,
+            geo::kernels::Orientation::Collinear => {
+                let (oa, ob) = (*a - *origin, *b - *origin);
+                if oa.dot(ob) < 0.0 {
+                    // opposite directions along the vertical line through the origin, the upper one first
+                    b.y().total_cmp(&a.y())
+                } else {
+                    let squared = |vector: geo::Point<f64>| vector.dot(vector);
+                    squared(oa).total_cmp(&squared(ob))
+                }
+            }
+        }
+    })
+}
+
+/// Returns the segment starting at the point of the end event at the given index, if any.
+///
+/// The segments then join at the point, one on each side of the ray from the origin going through it.
+fn joined_segment(events: &[VisibilityEvent], index: usize) -> Option<geo::Line<f64>> {
+    let point = events[index].point();
+    events[index + 1..]
+        .iter()
+        .take_while(|event| event.point() == point)
+        .find(|event| event.event_type == VisibilityEventType::StartVertex)
+        .map(|event| event.segment)
+}
+
 /// Warning: this is not the real polygon visibility but the union of its vertices visibility
+///
+/// When this union has several components, only the largest one is returned, see [`polygon_visibility_regions`]
+/// to get all of them, and [`SampledVisibility`](crate::SampledVisibility) for a finer approximation.
 impl<T> Visibility<T> for geo::Polygon<f64>
 where
-    geo::Point<f64>: Visibility<T>,
+    geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
 {
+    type Output = geo::Polygon<f64>;
+
     fn visibility(&self, obstacles: &T) -> geo::Polygon<f64> {
-        let mut visibility_polygon = geo::MultiPolygon(Vec::new());
-        for point in self.exterior().points().skip(1) {
-            let polygon = point.visibility(obstacles);
-            visibility_polygon = visibility_polygon.union(&polygon, 1000.0);
-        }
-        visibility_polygon
-            .0
-            .first()
-            .cloned()
-            .unwrap_or_else(|| geo::Polygon::new(geo::LineString(vec![]), vec![]))
+        polygon_visibility(self, obstacles, BooleanBackend::default())
+    }
+}
+
+/// Computes the union of the visibility polygons of the polygon vertices with the given boolean operations,
+/// keeping its largest component.
+///
+/// This is what [`Visibility::visibility`] returns for a polygon, with the default [`BooleanBackend`].
+/// A warning is logged when other components are dropped.
+///
+/// # Example
+///
+/// ```
+/// use geo::{polygon, Area};
+/// use geo_visibility::{polygon_visibility, BooleanBackend};
+///
+/// // a room of a hundredth of a degree and a desk in it
+/// let room = polygon![
+///     (x: 2.35, y: 48.85),
+///     (x: 2.36, y: 48.85),
+///     (x: 2.36, y: 48.86),
+///     (x: 2.35, y: 48.86),
+/// ];
+/// let desk = polygon![
+///     (x: 2.352, y: 48.852),
+///     (x: 2.353, y: 48.852),
+///     (x: 2.353, y: 48.853),
+///     (x: 2.352, y: 48.853),
+/// ];
+///
+/// let visibility = polygon_visibility(&desk, &room, BooleanBackend::Geo);
+///
+/// assert!((visibility.unsigned_area() - room.unsigned_area()).abs() < 1E-12);
+/// ```
+pub fn polygon_visibility<T>(
+    viewer: &geo::Polygon<f64>,
+    obstacles: &T,
+    backend: BooleanBackend,
+) -> geo::Polygon<f64>
+where
+    T: ?Sized,
+    geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+{
+    let regions = polygon_visibility_regions(viewer, obstacles, backend);
+    if regions.0.len() > 1 {
+        warn!(
+            "polygon visibility has {} components, only the largest one is kept",
+            regions.0.len()
+        );
+    }
+    regions
+        .0
+        .into_iter()
+        .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))
+        .unwrap_or_else(|| geo::Polygon::new(geo::LineString(vec![]), vec![]))
+}
+
+/// Computes the union of the visibility polygons of the polygon vertices with the given boolean operations,
+/// with all its components and their holes.
+///
+/// # Example
+///
+/// ```
+/// use geo::{polygon, Area, MultiPolygon};
+/// use geo_visibility::{polygon_visibility, polygon_visibility_regions, BooleanBackend};
+///
+/// // two closed rooms, the viewer has vertices in both of them
+/// let rooms = MultiPolygon(vec![
+///     polygon![(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 4.0)],
+///     polygon![(x: 6.0, y: 0.0), (x: 9.0, y: 0.0), (x: 9.0, y: 3.0), (x: 6.0, y: 3.0)],
+/// ]);
+/// let viewer = polygon![(x: 1.0, y: 1.0), (x: 2.0, y: 1.0), (x: 8.0, y: 2.0)];
+///
+/// let regions = polygon_visibility_regions(&viewer, &rooms, BooleanBackend::default());
+/// let largest = polygon_visibility(&viewer, &rooms, BooleanBackend::default());
+///
+/// assert_eq!(regions.0.len(), 2);
+/// assert!((largest.unsigned_area() - 16.0).abs() < 1E-3);
+/// ```
+pub fn polygon_visibility_regions<T>(
+    viewer: &geo::Polygon<f64>,
+    obstacles: &T,
+    backend: BooleanBackend,
+) -> geo::MultiPolygon<f64>
+where
+    T: ?Sized,
+    geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+{
+    let mut visibility_polygon = geo::MultiPolygon(Vec::new());
+    for point in viewer.exterior().points().skip(1) {
+        let polygon = point.visibility(obstacles);
+        visibility_polygon = backend.union(&visibility_polygon, &polygon);
+    }
+    visibility_polygon
+}
+
+/// The points see the union of their visibility polygons.
+impl<T: ?Sized> Visibility<T> for geo::MultiPoint<f64>
+where
+    geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+{
+    type Output = geo::MultiPolygon<f64>;
+
+    fn visibility(&self, obstacles: &T) -> geo::MultiPolygon<f64> {
+        self.0
+            .iter()
+            .fold(geo::MultiPolygon(Vec::new()), |union, point| {
+                BooleanBackend::default().union(&union, &point.visibility(obstacles))
+            })
+    }
+}
+
+/// Each point sees its own visibility polygon.
+impl<T: ?Sized> Visibility<T> for [geo::Point<f64>]
+where
+    geo::Point<f64>: Visibility<T, Output = geo::Polygon<f64>>,
+{
+    type Output = Vec<geo::Polygon<f64>>;
+
+    fn visibility(&self, obstacles: &T) -> Vec<geo::Polygon<f64>> {
+        self.iter()
+            .map(|point| point.visibility(obstacles))
+            .collect()
     }
 }
 
@@ -196,8 +776,7 @@ where
 mod tests {
     use super::*;
     use data_uri_utils::svg_str_to_data_uri;
-    use geo::{Coord, Line};
-    use geo_rand::{GeoRand, GeoRandParameters};
+    use geo::{polygon, Coord, Line};
     use geo_svg::{Color, ToSvg};
 
     fn test_visibility(
@@ -375,8 +954,293 @@ mod tests {
         );
     }
 
+    #[test]
+    fn calculate_visibility_polygon_with_crossing_and_overlapping_segments() {
+        let lines: Vec<_> = [
+            [[0.0, 0.0], [4.0, 0.0]],
+            [[4.0, 0.0], [4.0, 4.0]],
+            [[4.0, 4.0], [0.0, 4.0]],
+            [[0.0, 4.0], [0.0, 0.0]],
+            [[2.0, 0.0], [2.0, 4.0]],
+            [[2.0, 1.0], [2.0, 3.0]],
+            [[1.5, 2.0], [2.5, 2.0]],
+            [[3.0, 3.0], [3.0, 3.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect();
+
+        let visibility = geo::Point::new(1.0, 1.0).visibility(lines.as_slice());
+
+        // the wall at x = 2 hides the right half and the crossing wall casts a triangular shadow
+        assert_relative_eq!(visibility.unsigned_area(), 7.75, epsilon = 1E-9);
+    }
+
+    fn domain_with_hole() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn see_a_half_plane_from_a_wall() {
+        let domain = domain_with_hole();
+        let viewpoint = geo::Point::new(5.0, 4.0);
+
+        let front = VisibilityPolygon::new(viewpoint, &domain);
+        let back = VisibilityPolygon::mounted(viewpoint, &domain, MountSide::Right);
+
+        assert_eq!(front.viewpoint, viewpoint);
+        assert_relative_eq!(front.polygon.unsigned_area(), 40.0, epsilon = 1E-6);
+        assert_relative_eq!(back.polygon.unsigned_area(), 8.0, epsilon = 1E-6);
+    }
+
+    #[test]
+    fn see_a_half_plane_from_a_loose_wall_in_a_room() {
+        use geo::Contains;
+
+        let room =
+            polygon![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)];
+        let wall = Line::new(Coord { x: 5.0, y: 2.0 }, Coord { x: 5.0, y: 8.0 });
+        let obstacles = vec![geo::Geometry::Polygon(room), geo::Geometry::Line(wall)];
+        let viewpoint = geo::Point::new(5.0, 5.0);
+
+        let left = VisibilityPolygon::new(viewpoint, &obstacles);
+        let right = VisibilityPolygon::mounted(viewpoint, &obstacles, MountSide::Right);
+
+        assert_relative_eq!(left.polygon.unsigned_area(), 50.0, epsilon = 1E-6);
+        assert_relative_eq!(right.polygon.unsigned_area(), 50.0, epsilon = 1E-6);
+        assert!(left.polygon.contains(&geo::Point::new(2.0, 5.0)));
+        assert!(right.polygon.contains(&geo::Point::new(8.0, 5.0)));
+    }
+
+    #[test]
+    fn see_a_wedge_from_a_vertex() {
+        let domain = domain_with_hole();
+
+        // the quadrant behind the hole corner is hidden, apart from the hole itself
+        let visibility = geo::Point::new(3.0, 4.0).visibility(&domain);
+        assert_relative_eq!(visibility.unsigned_area(), 92.0 - 34.0, epsilon = 1E-6);
+
+        let corner = geo::Point::new(10.0, 10.0).visibility(&domain);
+        // the hole casts a shadow reaching the bottom and left walls
+        assert_relative_eq!(corner.unsigned_area(), 452.0 / 7.0, epsilon = 1E-6);
+    }
+
+    #[test]
+    fn apply_the_corner_policy_to_grazing_rays() {
+        // a corner pointing at the viewpoint in front of a far wall
+        let lines: Vec<_> = [
+            [[3.0, -1.0], [2.0, 0.0]],
+            [[2.0, 0.0], [3.0, 1.0]],
+            [[5.0, -5.0], [5.0, 5.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect();
+        let viewpoint = geo::Point::new(0.0, 0.0);
+        let behind = Coord { x: 5.0, y: 0.0 };
+
+        let closed = VisibilityPolygon::with_corner_policy(
+            viewpoint,
+            lines.as_slice(),
+            CornerPolicy::Closed,
+        );
+        let open =
+            VisibilityPolygon::with_corner_policy(viewpoint, lines.as_slice(), CornerPolicy::Open);
+
+        assert!(!closed.polygon.exterior().0.contains(&behind));
+        assert!(open.polygon.exterior().0.contains(&behind));
+        assert_eq!(open.edges.len(), open.polygon.exterior().0.len() - 1);
+        assert!(open.edges.contains(&EdgeSource::Window(0)));
+        assert!(open.edges.contains(&EdgeSource::Window(1)));
+        assert_eq!(closed, VisibilityPolygon::new(viewpoint, lines.as_slice()));
+    }
+
+    #[test]
+    fn do_not_depend_on_the_order_of_events() {
+        let lines: Vec<_> = [
+            [[3.0, -1.0], [2.0, 0.0]],
+            [[2.0, 0.0], [3.0, 1.0]],
+            [[2.0, 0.0], [4.0, 0.5]],
+            [[4.0, -4.0], [4.0, 4.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect();
+        let origin = geo::Point::new(0.0, 0.0);
+
+        let (_, mut events) = sweep_state(&origin, &lines);
+        let mut reversed: Vec<_> = events.iter().rev().cloned().collect();
+        sort_events_by_angle(&origin, &mut events);
+        sort_events_by_angle(&origin, &mut reversed);
+
+        assert_eq!(events, reversed);
+    }
+
+    #[test]
+    fn accept_any_geometry_with_segments() {
+        let room = geo::Rect::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 4.0, y: 4.0 });
+        let walls: geo::MultiLineString<f64> = geo::MultiLineString(vec![
+            geo::LineString::from(vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0)]),
+            geo::LineString::from(vec![(4.0, 4.0), (0.0, 4.0), (0.0, 0.0)]),
+        ]);
+        let furniture = geo::Triangle::new(
+            Coord { x: 2.0, y: 1.0 },
+            Coord { x: 3.0, y: 1.0 },
+            Coord { x: 3.0, y: 3.0 },
+        );
+        let viewpoint = geo::Point::new(1.0, 2.0);
+
+        assert_relative_eq!(viewpoint.visibility(&room).unsigned_area(), 16.0);
+        assert_relative_eq!(viewpoint.visibility(&walls).unsigned_area(), 16.0);
+        assert_eq!(
+            viewpoint.visibility(&vec![room, room]),
+            viewpoint.visibility(&room)
+        );
+
+        let collection = geo::GeometryCollection(vec![
+            geo::Geometry::Rect(room),
+            geo::Geometry::Triangle(furniture),
+            geo::Geometry::Point(geo::Point::new(1.5, 2.0)),
+        ]);
+        let expected = viewpoint.visibility(&vec![
+            geo::Geometry::Rect(room),
+            geo::Geometry::Triangle(furniture),
+        ]);
+        assert_eq!(viewpoint.visibility(&collection), expected);
+        assert_eq!(
+            viewpoint.visibility(&geo::Geometry::GeometryCollection(collection)),
+            expected
+        );
+        assert!(expected.unsigned_area() < 16.0 - 2.0);
+
+        let streamed = Walls(walls.0.iter().flat_map(|line_string| {
+            line_string
+                .0
+                .windows(2)
+                .map(|pair| Line::new(pair[0], pair[1]))
+        }));
+        assert_eq!(
+            viewpoint.visibility(&streamed),
+            viewpoint.visibility(&walls)
+        );
+    }
+
+    #[test]
+    fn see_amongst_polygons_with_holes() {
+        let obstacles = geo::MultiPolygon(vec![
+            domain_with_hole(),
+            polygon!(
+                exterior: [
+                    (x: 20.0, y: 0.0),
+                    (x: 30.0, y: 0.0),
+                    (x: 30.0, y: 10.0),
+                    (x: 20.0, y: 10.0),
+                ],
+                interiors: [[
+                    (x: 23.0, y: 4.0),
+                    (x: 27.0, y: 4.0),
+                    (x: 27.0, y: 6.0),
+                    (x: 23.0, y: 6.0),
+                ]],
+            ),
+        ]);
+        let viewpoint = geo::Point::new(1.0, 5.0);
+
+        assert_eq!(
+            viewpoint.visibility(&obstacles),
+            viewpoint.visibility(&domain_with_hole())
+        );
+    }
+
+    #[test]
+    fn choose_the_side_of_raw_segments() {
+        // a clockwise room seen from a camera on its bottom wall
+        let lines: Vec<_> = [
+            [[0.0, 0.0], [0.0, 4.0]],
+            [[0.0, 4.0], [4.0, 4.0]],
+            [[4.0, 4.0], [4.0, 0.0]],
+            [[4.0, 0.0], [0.0, 0.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect();
+        let viewpoint = geo::Point::new(2.0, 0.0);
+
+        let inside = VisibilityPolygon::mounted(viewpoint, lines.as_slice(), MountSide::Right);
+
+        assert_relative_eq!(inside.polygon.unsigned_area(), 16.0, epsilon = 1E-6);
+    }
+
+    #[test]
+    fn track_the_origin_of_visibility_polygon_edges() {
+        let lines: Vec<_> = [
+            [[-250.0, -250.0], [-250.0, 250.0]],
+            [[-250.0, 250.0], [250.0, 250.0]],
+            [[250.0, 250.0], [250.0, -250.0]],
+            [[250.0, -250.0], [-250.0, -250.0]],
+            [[-50.0, 50.0], [50.0, 50.0]],
+            [[50.0, 50.0], [50.0, -50.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect();
+
+        let visibility = VisibilityPolygon::new(geo::Point::new(0.0, 0.0), lines.as_slice());
+
+        assert_eq!(
+            visibility.polygon,
+            geo::Point::new(0.0, 0.0).visibility(lines.as_slice())
+        );
+        assert_eq!(
+            visibility.edges,
+            vec![
+                EdgeSource::Obstacle(5),
+                EdgeSource::Window(5),
+                EdgeSource::Obstacle(3),
+                EdgeSource::Obstacle(0),
+                EdgeSource::Window(4),
+                EdgeSource::Obstacle(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn mark_unbounded_edges_as_open() {
+        let lines = [
+            Line::new(Coord { x: 1.0, y: 1.0 }, Coord { x: 2.0, y: 0.0 }),
+            Line::new(Coord { x: 2.0, y: 0.0 }, Coord { x: 1.0, y: -1.0 }),
+        ];
+
+        let visibility = VisibilityPolygon::new(geo::Point::new(0.0, 0.0), &lines[..]);
+
+        assert_eq!(
+            visibility.edges,
+            vec![
+                EdgeSource::Obstacle(0),
+                EdgeSource::Obstacle(1),
+                EdgeSource::Open
+            ]
+        );
+    }
+
+    #[cfg(feature = "geo-clipper")]
     #[test]
     fn show_point_visibility() {
+        use geo_clipper::Clipper;
+        use geo_rand::{GeoRand, GeoRandParameters};
         use rand_core::SeedableRng;
         let mut rng = rand_pcg::Pcg64::seed_from_u64(2);
         let rect = geo::Rect::new(
@@ -409,8 +1273,11 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "geo-clipper")]
     #[test]
     fn show_polygon_vertices_visibility() {
+        use geo_clipper::Clipper;
+        use geo_rand::{GeoRand, GeoRandParameters};
         use rand_core::SeedableRng;
         let mut rng = rand_pcg::Pcg64::seed_from_u64(4);
         let rect = geo::Rect::new(
@@ -450,6 +1317,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sort_nearly_coinciding_events_whatever_their_order() {
+        let origin = geo::Point::new(0.0, 0.0);
+        let event = |y: f64, start: bool| {
+            let segment = geo::Line::new(Coord { x: 1.0, y }, Coord { x: 2.0, y: 1.0 + y });
+            if start {
+                VisibilityEvent::start(&segment)
+            } else {
+                VisibilityEvent::end(&segment)
+            }
+        };
+        let events = [
+            event(0.0, true),
+            event(2E-16, false),
+            event(4E-16, true),
+            event(4E-16, false),
+        ];
+
+        let mut sorted = Vec::new();
+        for first in 0..events.len() {
+            let mut permuted = events.to_vec();
+            permuted.rotate_left(first);
+            permuted.swap(1, 3);
+            sort_events_by_angle(&origin, &mut permuted);
+            sorted.push(permuted);
+        }
+
+        assert!(sorted.iter().all(|events| *events == sorted[0]));
+        let ys: Vec<_> = sorted[0].iter().map(|event| event.point().y()).collect();
+        assert_eq!(ys, vec![4E-16, 4E-16, 2E-16, 0.0]);
+        assert_eq!(sorted[0][0].event_type, VisibilityEventType::EndVertex);
+    }
+
     #[test]
     fn test_sort_events_by_angle() {
         let mut events = vec![
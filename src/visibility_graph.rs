@@ -0,0 +1,615 @@
+use crate::angle_comparator::AngleComparator;
+use crate::comparable_line::ComparableLine;
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::orientation::Orientation;
+use crate::ray::Ray;
+use crate::utils::{approx_equal, cross};
+use crate::visibility::sweep_state;
+use crate::visibility_event::{VisibilityEvent, VisibilityEventType};
+use approx::relative_eq;
+use geo::{Distance, Euclidean};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+/// Graph connecting every pair of mutually visible obstacle vertices.
+///
+/// Edges are weighted by the Euclidean distance between their endpoints.
+/// Rings of polygon obstacles bound the free space, so edges never cross the inside of a hole nor leave the polygon.
+/// A polygon on its own is thus a room: obstacles standing in an open space, such as building footprints,
+/// are wrapped in [`Solids`] so that edges go around them.
+/// The graph is built with Lee's algorithm: one angular sweep per vertex, in O(n² log n) overall.
+///
+/// [`Solids`]: crate::Solids
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::VisibilityGraph;
+///
+/// let domain = polygon![(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 4.0)];
+/// let graph = VisibilityGraph::new(&domain);
+///
+/// assert_eq!(graph.nodes().len(), 4);
+/// assert_eq!(graph.edge_count(), 6);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VisibilityGraph {
+    nodes: Vec<geo::Point<f64>>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+}
+
+impl VisibilityGraph {
+    /// Builds the visibility graph of the obstacle vertices.
+    pub fn new<T: Obstacles + ?Sized>(obstacles: &T) -> Self {
+        Self::with_points(obstacles, &[])
+    }
+
+    /// Builds the visibility graph of the obstacle vertices and of additional query points.
+    ///
+    /// Query points lying on an obstacle vertex are merged with it.
+    pub fn with_points<T: Obstacles + ?Sized>(obstacles: &T, points: &[geo::Point<f64>]) -> Self {
+        let segments: Vec<_> = ObstacleSegments::new(obstacles)
+            .pieces
+            .into_iter()
+            .map(|(segment, _)| segment)
+            .collect();
+
+        let mut nodes = Vec::new();
+        let mut index = HashMap::new();
+        for segment in &segments {
+            insert_node(&mut nodes, &mut index, segment.start.into());
+            insert_node(&mut nodes, &mut index, segment.end.into());
+        }
+        for point in points {
+            insert_node(&mut nodes, &mut index, *point);
+        }
+
+        let mut wedges = vec![Vec::new(); nodes.len()];
+        for ring in obstacles.rings() {
+            for wedge in ring_wedges(&ring) {
+                if let Some(&node) = index.get(&key(&wedge.vertex)) {
+                    wedges[node].push(wedge);
+                }
+            }
+        }
+
+        let mut adjacency = vec![Vec::new(); nodes.len()];
+        for (from, origin) in nodes.iter().enumerate() {
+            let mut previous: Option<geo::Point<f64>> = None;
+            for to in visible_nodes(origin, &nodes, &segments) {
+                let target = nodes[to];
+                // an edge going through another node is replaced by the two edges meeting there,
+                // whose ends are checked against the free space
+                let through_node = previous.is_some_and(|previous| {
+                    Orientation::from(*origin, previous, target) == Orientation::Collinear
+                        && (previous - *origin).dot(target - *origin) > 0.0
+                });
+                previous = Some(target);
+                if to <= from || through_node {
+                    continue;
+                }
+                if wedges[from].iter().any(|wedge| wedge.blocks(&target))
+                    || wedges[to].iter().any(|wedge| wedge.blocks(origin))
+                {
+                    continue;
+                }
+                let weight = Euclidean::distance(origin, &target);
+                adjacency[from].push((to, weight));
+                adjacency[to].push((from, weight));
+            }
+        }
+
+        Self { nodes, adjacency }
+    }
+
+    /// Returns the graph nodes, obstacle vertices first then query points.
+    pub fn nodes(&self) -> &[geo::Point<f64>] {
+        &self.nodes
+    }
+
+    /// Returns the index of the node located at the given point.
+    pub fn node(&self, point: &geo::Point<f64>) -> Option<usize> {
+        self.nodes.iter().position(|node| approx_equal(node, point))
+    }
+
+    /// Returns the nodes visible from the given node along with their distance.
+    pub fn neighbors(&self, node: usize) -> &[(usize, f64)] {
+        &self.adjacency[node]
+    }
+
+    /// Returns the number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.adjacency.iter().map(Vec::len).sum::<usize>() / 2
+    }
+
+    /// Returns every edge once as `(from, to, distance)` with `from < to`.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(from, neighbors)| {
+                neighbors
+                    .iter()
+                    .filter(move |(to, _)| from < *to)
+                    .map(move |(to, weight)| (from, *to, *weight))
+            })
+    }
+
+    /// Returns the nodes along the shortest path between two nodes, found with A*.
+    ///
+    /// Returns `None` if no path connects them.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let (distances, previous) = self.search(from, Some(to));
+        if distances[to].is_infinite() {
+            return None;
+        }
+        let mut path = vec![to];
+        while let Some(node) = previous[*path.last().unwrap()] {
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Returns the shortest path tree rooted at the given node, found with Dijkstra's algorithm.
+    ///
+    /// The first vector holds the distance of each node to the root, infinite if it is not reachable.
+    /// The second one holds the node preceding each node on its shortest path.
+    pub fn shortest_path_tree(&self, from: usize) -> (Vec<f64>, Vec<Option<usize>>) {
+        self.search(from, None)
+    }
+
+    /// Runs A* towards the goal if any, Dijkstra's algorithm over the whole graph otherwise.
+    fn search(&self, from: usize, to: Option<usize>) -> (Vec<f64>, Vec<Option<usize>>) {
+        let heuristic = |node: usize| {
+            to.map_or(0.0, |to| {
+                Euclidean::distance(&self.nodes[node], &self.nodes[to])
+            })
+        };
+        let mut distances = vec![f64::INFINITY; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        let mut queue = BinaryHeap::new();
+
+        distances[from] = 0.0;
+        queue.push(Candidate {
+            estimate: heuristic(from),
+            node: from,
+        });
+
+        while let Some(Candidate { node, .. }) = queue.pop() {
+            if Some(node) == to {
+                break;
+            }
+            for &(neighbor, weight) in &self.adjacency[node] {
+                let distance = distances[node] + weight;
+                if distance < distances[neighbor] {
+                    distances[neighbor] = distance;
+                    previous[neighbor] = Some(node);
+                    queue.push(Candidate {
+                        estimate: distance + heuristic(neighbor),
+                        node: neighbor,
+                    });
+                }
+            }
+        }
+
+        (distances, previous)
+    }
+
+    /// Converts the graph to a `petgraph` undirected graph with the same node indices.
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::graph::UnGraph<geo::Point<f64>, f64> {
+        let mut graph =
+            petgraph::graph::UnGraph::with_capacity(self.nodes.len(), self.edge_count());
+        let indices: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|node| graph.add_node(*node))
+            .collect();
+        for (from, to, weight) in self.edges() {
+            graph.add_edge(indices[from], indices[to], weight);
+        }
+        graph
+    }
+}
+
+/// Node waiting in the A* queue, ordered so that the smallest estimate is popped first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    estimate: f64,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+fn key(point: &geo::Point<f64>) -> (u64, u64) {
+    // adding 0.0 turns -0.0 into 0.0
+    ((point.x() + 0.0).to_bits(), (point.y() + 0.0).to_bits())
+}
+
+fn insert_node(
+    nodes: &mut Vec<geo::Point<f64>>,
+    index: &mut HashMap<(u64, u64), usize>,
+    point: geo::Point<f64>,
+) -> usize {
+    *index.entry(key(&point)).or_insert_with(|| {
+        nodes.push(point);
+        nodes.len() - 1
+    })
+}
+
+/// Angular range around a ring vertex lying outside the free space.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Wedge {
+    pub vertex: geo::Point<f64>,
+    pub previous: geo::Point<f64>,
+    pub next: geo::Point<f64>,
+}
+
+impl Wedge {
+    /// Returns whether the free space spans more than a half-turn around the vertex.
+    ///
+    /// Shortest paths can only bend around such vertices.
+    pub fn is_reflex(&self) -> bool {
+        cross(self.previous - self.vertex, self.next - self.vertex) > 0.0
+    }
+
+    /// Returns the unit vector splitting the free space around the vertex in two equal angles.
+    pub fn free_direction(&self) -> geo::Point<f64> {
+        let a = self.next - self.vertex;
+        let b = self.previous - self.vertex;
+        let mut angle = cross(a, b).atan2(a.dot(b));
+        if angle <= 0.0 {
+            angle += 2.0 * std::f64::consts::PI;
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let a = a / a.x().hypot(a.y());
+        geo::Point::new(a.x() * cos - a.y() * sin, a.x() * sin + a.y() * cos)
+    }
+
+    /// Returns whether the segment from the vertex to the target starts outside the free space.
+    ///
+    /// The blocked range goes counterclockwise from the previous ring vertex to the next one, both excluded.
+    pub fn blocks(&self, target: &geo::Point<f64>) -> bool {
+        let a = self.previous - self.vertex;
+        let b = self.next - self.vertex;
+        let d = *target - self.vertex;
+        if cross(a, b) >= 0.0 {
+            cross(a, d) > 0.0 && cross(d, b) > 0.0
+        } else {
+            !(cross(b, d) >= 0.0 && cross(d, a) >= 0.0)
+        }
+    }
+}
+
+pub(crate) fn ring_wedges(ring: &geo::LineString<f64>) -> Vec<Wedge> {
+    let mut points: Vec<geo::Point<f64>> = ring.points().collect();
+    points.dedup();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    (0..points.len())
+        .map(|i| Wedge {
+            vertex: points[i],
+            previous: points[(i + points.len() - 1) % points.len()],
+            next: points[(i + 1) % points.len()],
+        })
+        .collect()
+}
+
+enum SweepItem {
+    Event(VisibilityEvent),
+    Probe(usize, geo::Point<f64>),
+}
+
+impl SweepItem {
+    fn point(&self) -> geo::Point<f64> {
+        match self {
+            SweepItem::Event(event) => event.point(),
+            SweepItem::Probe(_, point) => *point,
+        }
+    }
+
+    /// Order of the items sharing the same point: segments ending there are removed before probing
+    /// and segments starting there are inserted after.
+    fn rank(&self) -> u8 {
+        match self {
+            SweepItem::Event(VisibilityEvent {
+                event_type: VisibilityEventType::EndVertex,
+                ..
+            }) => 0,
+            SweepItem::Probe(..) => 1,
+            SweepItem::Event(VisibilityEvent {
+                event_type: VisibilityEventType::StartVertex,
+                ..
+            }) => 2,
+        }
+    }
+}
+
+/// Returns the indices of the nodes that no segment hides from the origin.
+pub(crate) fn visible_nodes(
+    origin: &geo::Point<f64>,
+    nodes: &[geo::Point<f64>],
+    segments: &[geo::Line<f64>],
+) -> Vec<usize> {
+    let (mut state, events) = sweep_state(origin, segments);
+    let mut items: Vec<_> = events
+        .into_iter()
+        .map(SweepItem::Event)
+        .chain(
+            nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| !approx_equal(node, origin))
+                .map(|(index, node)| SweepItem::Probe(index, *node)),
+        )
+        .collect();
+
+    let angle_comparator = AngleComparator { origin: *origin };
+    items.sort_by(|a, b| {
+        if approx_equal(&a.point(), &b.point()) {
+            a.rank().cmp(&b.rank())
+        } else {
+            angle_comparator.cmp(&a.point(), &b.point())
+        }
+    });
+
+    let mut visible = Vec::new();
+    for item in items {
+        match item {
+            SweepItem::Event(event) => {
+                let segment = ComparableLine::new(*origin, event.segment);
+                match event.event_type {
+                    VisibilityEventType::StartVertex => {
+                        state.insert(segment);
+                    }
+                    VisibilityEventType::EndVertex => {
+                        state.remove(&segment);
+                    }
+                }
+            }
+            SweepItem::Probe(index, target) => {
+                if !is_hidden(origin, &target, &state) {
+                    visible.push(index);
+                }
+            }
+        }
+    }
+    visible
+}
+
+/// Returns whether the closest segment of the sweep state lies strictly between the origin and the target.
+fn is_hidden(
+    origin: &geo::Point<f64>,
+    target: &geo::Point<f64>,
+    state: &BTreeSet<ComparableLine>,
+) -> bool {
+    state.iter().next().is_some_and(|closest| {
+        Ray::new(geo::Line::new(*origin, *target))
+            .intersects(&closest.line)
+            .is_some_and(|intersection| {
+                let hit = Euclidean::distance(origin, &intersection);
+                let distance = Euclidean::distance(origin, target);
+                hit < distance && !relative_eq!(hit, distance, max_relative = 1E-9)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{point, polygon};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    fn is_edge(graph: &VisibilityGraph, [ax, ay]: [f64; 2], [bx, by]: [f64; 2]) -> bool {
+        let a = graph.node(&point!(x: ax, y: ay)).unwrap();
+        let b = graph.node(&point!(x: bx, y: by)).unwrap();
+        graph.neighbors(a).iter().any(|(node, _)| *node == b)
+    }
+
+    #[test]
+    fn connect_mutually_visible_vertices() {
+        let graph = VisibilityGraph::new(&domain());
+
+        assert_eq!(graph.nodes().len(), 8);
+        assert!(is_edge(&graph, [0.0, 0.0], [10.0, 0.0]));
+        assert!(is_edge(&graph, [0.0, 0.0], [7.0, 4.0]));
+        assert!(is_edge(&graph, [0.0, 0.0], [3.0, 6.0]));
+        assert!(is_edge(&graph, [3.0, 4.0], [7.0, 4.0]));
+        assert!(!is_edge(&graph, [0.0, 0.0], [10.0, 10.0]));
+        assert!(!is_edge(&graph, [0.0, 0.0], [7.0, 6.0]));
+    }
+
+    #[test]
+    fn do_not_cross_the_inside_of_holes() {
+        let graph = VisibilityGraph::new(&domain());
+
+        assert!(!is_edge(&graph, [3.0, 4.0], [7.0, 6.0]));
+        assert!(!is_edge(&graph, [7.0, 4.0], [3.0, 6.0]));
+    }
+
+    #[test]
+    fn do_not_leave_a_concave_domain() {
+        let domain = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 4.0, y: 0.0),
+            (x: 4.0, y: 1.0),
+            (x: 1.0, y: 1.0),
+            (x: 1.0, y: 4.0),
+            (x: 0.0, y: 4.0),
+        ];
+        let graph = VisibilityGraph::new(&domain);
+
+        assert!(!is_edge(&graph, [4.0, 1.0], [1.0, 4.0]));
+        assert!(is_edge(&graph, [4.0, 0.0], [1.0, 1.0]));
+        assert!(is_edge(&graph, [0.0, 0.0], [1.0, 1.0]));
+    }
+
+    #[test]
+    fn go_around_a_pillar_of_a_multi_polygon() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 10.0, y: 0.0),
+            (x: 10.0, y: 10.0),
+            (x: 0.0, y: 10.0),
+        ];
+        let pillar =
+            polygon![(x: 4.0, y: 3.0), (x: 6.0, y: 3.0), (x: 6.0, y: 7.0), (x: 4.0, y: 7.0)];
+        let graph = VisibilityGraph::with_points(
+            &geo::MultiPolygon(vec![room, pillar]),
+            &[point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)],
+        );
+
+        assert!(!is_edge(&graph, [4.0, 3.0], [6.0, 7.0]));
+        assert!(is_edge(&graph, [1.0, 5.0], [4.0, 3.0]));
+        let from = graph.node(&point!(x: 1.0, y: 5.0)).unwrap();
+        let to = graph.node(&point!(x: 9.0, y: 5.0)).unwrap();
+        let (distances, _) = graph.shortest_path_tree(from);
+        assert!(relative_eq!(distances[to], 2.0 + 2.0 * 13.0_f64.sqrt()));
+    }
+
+    #[test]
+    fn go_around_furniture_standing_in_a_room() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 10.0, y: 0.0),
+            (x: 10.0, y: 10.0),
+            (x: 0.0, y: 10.0),
+        ];
+        let queries = [point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)];
+        let distance = |obstacles: Vec<geo::Geometry<f64>>| {
+            let graph = VisibilityGraph::with_points(&obstacles, &queries);
+            let from = graph.node(&queries[0]).unwrap();
+            let (distances, _) = graph.shortest_path_tree(from);
+            distances[graph.node(&queries[1]).unwrap()]
+        };
+
+        let table = geo::Rect::new(geo::Coord { x: 4.0, y: 3.0 }, geo::Coord { x: 6.0, y: 7.0 });
+        let stool = geo::Triangle::new(
+            geo::Coord { x: 4.0, y: 3.0 },
+            geo::Coord { x: 6.0, y: 3.0 },
+            geo::Coord { x: 5.0, y: 7.0 },
+        );
+
+        assert!(relative_eq!(
+            distance(vec![room.clone().into(), table.into()]),
+            2.0 + 2.0 * 13.0_f64.sqrt()
+        ));
+        assert!(relative_eq!(
+            distance(vec![room.into(), stool.into()]),
+            4.0 * 5.0_f64.sqrt()
+        ));
+    }
+
+    #[test]
+    fn go_around_solids_in_open_space() {
+        let building =
+            polygon![(x: 2.0, y: -1.0), (x: 4.0, y: -1.0), (x: 4.0, y: 1.0), (x: 2.0, y: 1.0)];
+
+        let room = VisibilityGraph::new(&building);
+        let solid = VisibilityGraph::new(&crate::Solids(building));
+
+        assert_eq!(room.edge_count(), 6);
+        assert_eq!(solid.edge_count(), 4);
+        assert!(!is_edge(&solid, [2.0, -1.0], [4.0, 1.0]));
+        assert!(is_edge(&solid, [2.0, -1.0], [4.0, -1.0]));
+    }
+
+    #[test]
+    fn connect_query_points() {
+        let graph = VisibilityGraph::with_points(
+            &domain(),
+            &[point!(x: 1.0, y: 5.0), point!(x: 9.0, y: 5.0)],
+        );
+
+        assert_eq!(graph.nodes().len(), 10);
+        assert!(is_edge(&graph, [1.0, 5.0], [3.0, 4.0]));
+        assert!(is_edge(&graph, [1.0, 5.0], [0.0, 10.0]));
+        assert!(!is_edge(&graph, [1.0, 5.0], [9.0, 5.0]));
+        assert!(!is_edge(&graph, [1.0, 5.0], [7.0, 6.0]));
+    }
+
+    #[test]
+    fn edge_weights_are_euclidean_distances() {
+        let graph = VisibilityGraph::new(&domain());
+
+        for (from, to, weight) in graph.edges() {
+            assert!(relative_eq!(
+                weight,
+                Euclidean::distance(&graph.nodes()[from], &graph.nodes()[to])
+            ));
+        }
+    }
+
+    #[test]
+    fn find_shortest_path_between_nodes() {
+        let graph = VisibilityGraph::new(&domain());
+        let from = graph.node(&point!(x: 0.0, y: 0.0)).unwrap();
+        let to = graph.node(&point!(x: 10.0, y: 10.0)).unwrap();
+        let path = graph.shortest_path(from, to).unwrap();
+
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        assert!(graph.shortest_path(from, from).unwrap() == vec![from]);
+    }
+
+    #[test]
+    fn compute_shortest_path_tree() {
+        let graph = VisibilityGraph::new(&domain());
+        let root = graph.node(&point!(x: 0.0, y: 0.0)).unwrap();
+        let (distances, previous) = graph.shortest_path_tree(root);
+
+        let opposite = graph.node(&point!(x: 7.0, y: 6.0)).unwrap();
+        assert!(relative_eq!(distances[opposite], 65.0_f64.sqrt() + 2.0));
+        assert_eq!(previous[opposite], graph.node(&point!(x: 7.0, y: 4.0)));
+        assert_eq!(distances[root], 0.0);
+        assert_eq!(previous[root], None);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn export_to_petgraph() {
+        let graph = VisibilityGraph::new(&domain());
+        let petgraph = graph.to_petgraph();
+
+        assert_eq!(petgraph.node_count(), graph.nodes().len());
+        assert_eq!(petgraph.edge_count(), graph.edge_count());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::guard_placement::{GuardCandidates, GuardCandidatesError};
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::star_polygon::StarPolygon;
+use crate::utils::map_indices;
+use crate::visibility::VisibilityPolygon;
+use std::collections::VecDeque;
+
+/// Space syntax measures of a sample point of a visibility graph analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VgaMeasures {
+    /// Number of other points visible from the point.
+    pub connectivity: usize,
+    /// Fraction of the pairs of visible points that see each other, zero with less than two visible points.
+    pub clustering: f64,
+    /// Average number of visual steps needed to reach the other points connected to the point.
+    pub mean_depth: f64,
+    /// Integration of Hillier and Hanson: reciprocal of the relative asymmetry normalized with the diamond value.
+    ///
+    /// Zero when less than three points are connected, infinite when every connected point is visible.
+    pub integration: f64,
+}
+
+/// Visibility graph analysis of a plan, as used in space syntax.
+///
+/// Sample points are linked when they see each other. The visibility polygon of each point is computed once
+/// and the other points are tested against it with a containment test relying on its star shape.
+/// With the `rayon` feature, the points are processed in parallel.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::VisibilityGraphAnalysis;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+///
+/// let vga = VisibilityGraphAnalysis::grid(&domain, 1.0).unwrap();
+///
+/// assert_eq!(vga.points.len(), 7);
+/// // the corner sees every other cell
+/// assert_eq!(vga.measures[0].connectivity, 6);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibilityGraphAnalysis {
+    /// Sample points.
+    pub points: Vec<geo::Point<f64>>,
+    /// Indices of the points seen from each point, in increasing order.
+    pub neighbors: Vec<Vec<usize>>,
+    /// Measures of each point.
+    pub measures: Vec<VgaMeasures>,
+}
+
+impl VisibilityGraphAnalysis {
+    /// Analyses the visibility between the sample points amongst the obstacles.
+    pub fn new<T: Obstacles + ?Sized>(points: &[geo::Point<f64>], obstacles: &T) -> Self {
+        let segments = ObstacleSegments::new(obstacles);
+
+        // points sorted by abscissa so that only those within the bounds of a visibility polygon are tested
+        let mut by_x: Vec<_> = (0..points.len()).collect();
+        by_x.sort_by(|a, b| points[*a].x().total_cmp(&points[*b].x()));
+
+        let visible = map_indices(points.len(), |i| {
+            let viewpoint = points[i];
+            let star = StarPolygon::new(
+                viewpoint,
+                &VisibilityPolygon::among(viewpoint, &segments).polygon,
+            );
+            let bounds = match star.bounds() {
+                Some(bounds) => bounds,
+                None => return Vec::new(),
+            };
+            let first = by_x.partition_point(|j| points[*j].x() < bounds.min().x);
+            let mut visible: Vec<_> = by_x[first..]
+                .iter()
+                .take_while(|j| points[**j].x() <= bounds.max().x)
+                .filter(|j| **j != i && star.contains(&points[**j]))
+                .copied()
+                .collect();
+            visible.sort_unstable();
+            visible
+        });
+
+        // points on a window may be seen one way only, visibility is made symmetric
+        let mut neighbors = visible.clone();
+        for (i, visible) in visible.iter().enumerate() {
+            for j in visible {
+                neighbors[*j].push(i);
+            }
+        }
+        for neighbors in &mut neighbors {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+
+        let measures = map_indices(points.len(), |i| VgaMeasures {
+            connectivity: neighbors[i].len(),
+            clustering: clustering(&neighbors, i),
+            ..depth_measures(&neighbors, i)
+        });
+
+        Self {
+            points: points.to_vec(),
+            neighbors,
+            measures,
+        }
+    }
+
+    /// Analyses the visibility between the centres of the cells of a square grid lying inside the domain.
+    ///
+    /// Fails if the spacing is not positive and finite.
+    pub fn grid(domain: &geo::Polygon<f64>, spacing: f64) -> Result<Self, GuardCandidatesError> {
+        let points = GuardCandidates::Grid { spacing }.points(domain)?;
+        Ok(Self::new(&points, domain))
+    }
+}
+
+/// Returns the fraction of pairs of neighbours of the point that are neighbours themselves.
+fn clustering(neighbors: &[Vec<usize>], index: usize) -> f64 {
+    let around = &neighbors[index];
+    let k = around.len();
+    if k < 2 {
+        return 0.0;
+    }
+    let links: usize = around
+        .iter()
+        .map(|neighbor| {
+            neighbors[*neighbor]
+                .iter()
+                .filter(|other| around.binary_search(other).is_ok())
+                .count()
+        })
+        .sum();
+    // every link between two neighbours was counted from both ends
+    links as f64 / (k * (k - 1)) as f64
+}
+
+/// Returns the mean depth and integration of the point, computed with a breadth first search.
+fn depth_measures(neighbors: &[Vec<usize>], index: usize) -> VgaMeasures {
+    let mut depths = vec![usize::MAX; neighbors.len()];
+    depths[index] = 0;
+    let mut queue = VecDeque::from(vec![index]);
+    let (mut total, mut count) = (0, 1);
+    while let Some(node) = queue.pop_front() {
+        for neighbor in &neighbors[node] {
+            if depths[*neighbor] == usize::MAX {
+                depths[*neighbor] = depths[node] + 1;
+                total += depths[*neighbor];
+                count += 1;
+                queue.push_back(*neighbor);
+            }
+        }
+    }
+
+    let mean_depth = if count > 1 {
+        total as f64 / (count - 1) as f64
+    } else {
+        0.0
+    };
+    let integration = if count > 2 {
+        let k = count as f64;
+        let relative_asymmetry = 2.0 * (mean_depth - 1.0) / (k - 2.0);
+        let diamond = 2.0 * (k * (((k + 2.0) / 3.0).log2() - 1.0) + 1.0) / ((k - 1.0) * (k - 2.0));
+        diamond / relative_asymmetry
+    } else {
+        0.0
+    };
+
+    VgaMeasures {
+        connectivity: 0,
+        clustering: 0.0,
+        mean_depth,
+        integration,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::polygon;
+
+    #[test]
+    fn link_every_point_of_a_convex_room() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 3.0, y: 0.0),
+            (x: 3.0, y: 3.0),
+            (x: 0.0, y: 3.0),
+        ];
+        let vga = VisibilityGraphAnalysis::grid(&room, 1.0).unwrap();
+
+        assert_eq!(vga.points.len(), 9);
+        for measures in &vga.measures {
+            assert_eq!(measures.connectivity, 8);
+            assert_relative_eq!(measures.clustering, 1.0);
+            assert_relative_eq!(measures.mean_depth, 1.0);
+            assert_eq!(measures.integration, f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn measure_an_l_shaped_plan() {
+        let domain = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 4.0, y: 0.0),
+            (x: 4.0, y: 1.0),
+            (x: 1.0, y: 1.0),
+            (x: 1.0, y: 4.0),
+            (x: 0.0, y: 4.0),
+        ];
+        let vga = VisibilityGraphAnalysis::grid(&domain, 1.0).unwrap();
+
+        // cells from the corner along the bottom arm then up the left arm
+        assert_eq!(vga.points[0], geo::Point::new(0.5, 0.5));
+        assert_eq!(vga.points[3], geo::Point::new(3.5, 0.5));
+        assert_eq!(vga.points[6], geo::Point::new(0.5, 3.5));
+        assert_eq!(vga.neighbors[3], vec![0, 1, 2]);
+        assert_eq!(vga.measures[0].connectivity, 6);
+        assert_relative_eq!(vga.measures[0].mean_depth, 1.0);
+        assert_relative_eq!(vga.measures[3].mean_depth, 1.5);
+        assert_relative_eq!(vga.measures[3].clustering, 1.0);
+        assert!(vga.measures[0].clustering < 1.0);
+        assert!(vga.measures[0].integration > vga.measures[3].integration);
+    }
+
+    #[test]
+    fn keep_the_graph_symmetric() {
+        let domain = polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        );
+        let vga = VisibilityGraphAnalysis::grid(&domain, 1.0).unwrap();
+
+        for (i, neighbors) in vga.neighbors.iter().enumerate() {
+            for j in neighbors {
+                assert!(vga.neighbors[*j].contains(&i));
+            }
+        }
+        assert!(vga
+            .measures
+            .iter()
+            .all(|measures| measures.mean_depth <= 2.0));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::star_polygon::StarPolygon;
+use crate::utils::map_indices;
+use crate::visibility::VisibilityPolygon;
+use geo::kernels::{Kernel, Orientation, RobustKernel};
+use geo::line_intersection::{line_intersection, LineIntersection};
+use geo::{Distance, Euclidean};
+
+/// Number of targets up to which [`MatrixStrategy::Auto`] tests lines of sight.
+const SPARSE_TARGETS: usize = 8;
+
+/// Way of testing which targets an observer sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixStrategy {
+    /// Lines of sight for a few targets, visibility polygons otherwise.
+    Auto,
+    /// Computes the visibility polygon of each observer once and tests every target against it.
+    Polygons,
+    /// Tests the segment from each observer to each target against every obstacle.
+    LinesOfSight,
+}
+
+/// Mutual visibility between observers and targets.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon};
+/// use geo_visibility::VisibilityMatrix;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+/// let access_points = [point!(x: 0.5, y: 0.5), point!(x: 3.5, y: 0.5)];
+/// let desks = [point!(x: 3.0, y: 0.5), point!(x: 0.5, y: 3.0)];
+///
+/// let matrix = VisibilityMatrix::new(&access_points, &desks, &domain);
+///
+/// assert!(matrix.is_visible(0, 1));
+/// assert!(!matrix.is_visible(1, 1));
+/// assert_eq!(matrix.distance(1, 0), Some(0.5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibilityMatrix {
+    observers: usize,
+    targets: usize,
+    distances: Vec<Option<f64>>,
+}
+
+impl VisibilityMatrix {
+    /// Computes which targets each observer sees amongst the obstacles, picking the strategy automatically.
+    pub fn new<T: Obstacles + ?Sized>(
+        observers: &[geo::Point<f64>],
+        targets: &[geo::Point<f64>],
+        obstacles: &T,
+    ) -> Self {
+        Self::with_strategy(observers, targets, obstacles, MatrixStrategy::Auto)
+    }
+
+    /// Computes which targets each observer sees amongst the obstacles with the given strategy.
+    pub fn with_strategy<T: Obstacles + ?Sized>(
+        observers: &[geo::Point<f64>],
+        targets: &[geo::Point<f64>],
+        obstacles: &T,
+        strategy: MatrixStrategy,
+    ) -> Self {
+        let segments = ObstacleSegments::new(obstacles);
+        let strategy = match strategy {
+            MatrixStrategy::Auto if targets.len() <= SPARSE_TARGETS => MatrixStrategy::LinesOfSight,
+            MatrixStrategy::Auto => MatrixStrategy::Polygons,
+            strategy => strategy,
+        };
+
+        let rows = match strategy {
+            MatrixStrategy::LinesOfSight => map_indices(observers.len(), |i| {
+                targets
+                    .iter()
+                    .map(|target| line_of_sight(&observers[i], target, &segments.segments))
+                    .collect::<Vec<_>>()
+            }),
+            _ => map_indices(observers.len(), |i| {
+                let visibility = VisibilityPolygon::among(observers[i], &segments).polygon;
+                let star = StarPolygon::new(observers[i], &visibility);
+                targets
+                    .iter()
+                    .map(|target| star.contains(target))
+                    .collect::<Vec<_>>()
+            }),
+        };
+
+        let distances = rows
+            .iter()
+            .zip(observers)
+            .flat_map(|(row, observer)| {
+                row.iter().zip(targets).map(move |(visible, target)| {
+                    Some(Euclidean::distance(observer, target)).filter(|_| *visible)
+                })
+            })
+            .collect();
+
+        Self {
+            observers: observers.len(),
+            targets: targets.len(),
+            distances,
+        }
+    }
+
+    /// Returns the number of observers.
+    pub fn observers_count(&self) -> usize {
+        self.observers
+    }
+
+    /// Returns the number of targets.
+    pub fn targets_count(&self) -> usize {
+        self.targets
+    }
+
+    /// Returns whether the observer sees the target.
+    pub fn is_visible(&self, observer: usize, target: usize) -> bool {
+        self.distance(observer, target).is_some()
+    }
+
+    /// Returns the distance from the observer to the target if it is visible.
+    pub fn distance(&self, observer: usize, target: usize) -> Option<f64> {
+        self.row(observer)[target]
+    }
+
+    /// Returns the distance from the observer to every target, `None` for those it does not see.
+    pub fn row(&self, observer: usize) -> &[Option<f64>] {
+        &self.distances[observer * self.targets..(observer + 1) * self.targets]
+    }
+
+    /// Returns the indices of the targets the observer sees.
+    pub fn visible_targets(&self, observer: usize) -> impl Iterator<Item = usize> + '_ {
+        self.row(observer)
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| distance.is_some())
+            .map(|(index, _)| index)
+    }
+}
+
+/// Returns whether no obstacle crosses the segment from the observer to the target.
+///
+/// The segment may graze obstacles but not go through a vertex where walls meet on both of its sides.
+pub(crate) fn line_of_sight(
+    observer: &geo::Point<f64>,
+    target: &geo::Point<f64>,
+    segments: &[geo::Line<f64>],
+) -> bool {
+    let sight = geo::Line::new(observer.0, target.0);
+    let mut touched = Vec::new();
+    for segment in segments {
+        match line_intersection(sight, *segment) {
+            Some(LineIntersection::SinglePoint {
+                is_proper: true, ..
+            }) => return false,
+            Some(_) => touched.extend(
+                [segment.start, segment.end]
+                    .iter()
+                    .copied()
+                    .filter(|end| is_inside(&sight, end)),
+            ),
+            None => {}
+        }
+    }
+
+    !touched.iter().any(|vertex| {
+        let sides: Vec<_> = segments
+            .iter()
+            .filter_map(|segment| {
+                if segment.start == *vertex {
+                    Some(segment.end)
+                } else if segment.end == *vertex {
+                    Some(segment.start)
+                } else {
+                    None
+                }
+            })
+            .map(|end| RobustKernel::orient2d(sight.start, sight.end, end))
+            .collect();
+        sides.contains(&Orientation::CounterClockwise) && sides.contains(&Orientation::Clockwise)
+    })
+}
+
+/// Returns whether the point lies on the segment, strictly between its ends.
+fn is_inside(segment: &geo::Line<f64>, point: &geo::Coord<f64>) -> bool {
+    RobustKernel::orient2d(segment.start, segment.end, *point) == Orientation::Collinear
+        && geo::Point::from(*point - segment.start).dot((segment.end - segment.start).into()) > 0.0
+        && geo::Point::from(*point - segment.end).dot((segment.start - segment.end).into()) > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{point, polygon};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let domain = domain();
+        let points: Vec<_> = (0..10)
+            .flat_map(|i| (0..10).map(move |j| point!(x: i as f64 + 0.5, y: j as f64 + 0.5)))
+            .collect();
+        let observers = [point!(x: 1.0, y: 5.0), point!(x: 5.0, y: 2.0)];
+
+        let polygons =
+            VisibilityMatrix::with_strategy(&observers, &points, &domain, MatrixStrategy::Polygons);
+        let lines = VisibilityMatrix::with_strategy(
+            &observers,
+            &points,
+            &domain,
+            MatrixStrategy::LinesOfSight,
+        );
+
+        assert_eq!(polygons, lines);
+        assert_eq!(polygons.observers_count(), 2);
+        assert_eq!(polygons.targets_count(), 100);
+        assert!(polygons.is_visible(0, 9));
+        assert!(!polygons.is_visible(0, 95));
+    }
+
+    #[test]
+    fn do_not_see_through_a_corner() {
+        let segments = domain().segments();
+
+        // the diagonal of the hole only touches its corners
+        assert!(!line_of_sight(
+            &point!(x: 1.0, y: 3.0),
+            &point!(x: 9.0, y: 7.0),
+            &segments
+        ));
+        assert!(line_of_sight(
+            &point!(x: 2.0, y: 5.0),
+            &point!(x: 4.0, y: 3.0),
+            &segments
+        ));
+    }
+
+    #[test]
+    fn strategies_agree_on_a_corner_of_raw_segments() {
+        let corners = [(0.0, 0.0), (5.0, 0.0), (5.0, 5.0), (0.0, 5.0), (0.0, 0.0)];
+        let mut walls: Vec<_> = corners
+            .windows(2)
+            .map(|pair| geo::Line::new(geo::Coord::from(pair[0]), geo::Coord::from(pair[1])))
+            .collect();
+        // two walls meeting on the diagonal, on either side of it
+        walls.push(geo::Line::new(
+            geo::Coord { x: 2.0, y: 2.0 },
+            geo::Coord { x: 3.0, y: 0.5 },
+        ));
+        walls.push(geo::Line::new(
+            geo::Coord { x: 2.0, y: 2.0 },
+            geo::Coord { x: 1.0, y: 3.5 },
+        ));
+        let targets: Vec<_> = (0..5)
+            .flat_map(|i| (0..5).map(move |j| point!(x: i as f64 + 0.5, y: j as f64 + 0.5)))
+            .collect();
+        let observers = [point!(x: 0.5, y: 0.5)];
+
+        let polygons = VisibilityMatrix::with_strategy(
+            &observers,
+            &targets,
+            walls.as_slice(),
+            MatrixStrategy::Polygons,
+        );
+        let lines = VisibilityMatrix::with_strategy(
+            &observers,
+            &targets,
+            walls.as_slice(),
+            MatrixStrategy::LinesOfSight,
+        );
+
+        assert_eq!(polygons, lines);
+        // the diagonal goes through the corner
+        assert!(!lines.is_visible(0, 24));
+    }
+
+    #[test]
+    fn list_visible_targets() {
+        let matrix = VisibilityMatrix::new(
+            &[point!(x: 1.0, y: 5.0)],
+            &[
+                point!(x: 1.0, y: 1.0),
+                point!(x: 9.0, y: 5.0),
+                point!(x: 9.0, y: 9.0),
+            ],
+            &domain(),
+        );
+
+        assert_eq!(matrix.visible_targets(0).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(matrix.distance(0, 0), Some(4.0));
+        assert_eq!(matrix.row(0)[1], None);
+    }
+}
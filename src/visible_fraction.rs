@@ -0,0 +1,294 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::Obstacles;
+use crate::visibility::VisibilityPolygon;
+use geo::line_intersection::{line_intersection, LineIntersection};
+use geo::{Area, Distance, Euclidean};
+
+/// Part of a target seen from a viewpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleFraction<G> {
+    /// Visible part of the target.
+    pub part: G,
+    /// Area of the visible part for polygons, length for lines.
+    pub measure: f64,
+    /// Fraction of the target measure which is visible, zero for an empty target.
+    pub ratio: f64,
+}
+
+/// Geometry whose visible part can be measured.
+///
+/// Polygons are intersected with the visibility polygon with the given boolean operations while lines are split
+/// where they cross its edges.
+/// Lines running along an obstacle, like a sign on a wall, are visible where they touch the visibility polygon.
+pub trait Target {
+    /// Type of the visible part.
+    type Part;
+
+    /// Returns the part of the target inside the visibility polygon.
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> VisibleFraction<Self::Part>;
+}
+
+impl Target for geo::Polygon<f64> {
+    type Part = geo::MultiPolygon<f64>;
+
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> VisibleFraction<Self::Part> {
+        let part = backend.intersection(self, visibility);
+        fraction(part.unsigned_area(), self.unsigned_area(), part)
+    }
+}
+
+impl Target for geo::MultiPolygon<f64> {
+    type Part = geo::MultiPolygon<f64>;
+
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        backend: BooleanBackend,
+    ) -> VisibleFraction<Self::Part> {
+        let part = backend.intersection(self, visibility);
+        fraction(part.unsigned_area(), self.unsigned_area(), part)
+    }
+}
+
+impl Target for geo::Line<f64> {
+    type Part = geo::MultiLineString<f64>;
+
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        _: BooleanBackend,
+    ) -> VisibleFraction<Self::Part> {
+        let part = clip(visibility, std::iter::once(*self));
+        fraction(length(&part), length_of_line(self), part)
+    }
+}
+
+impl Target for geo::LineString<f64> {
+    type Part = geo::MultiLineString<f64>;
+
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        _: BooleanBackend,
+    ) -> VisibleFraction<Self::Part> {
+        let part = clip(visibility, self.lines());
+        fraction(
+            length(&part),
+            self.lines().map(|line| length_of_line(&line)).sum(),
+            part,
+        )
+    }
+}
+
+impl Target for geo::MultiLineString<f64> {
+    type Part = geo::MultiLineString<f64>;
+
+    fn visible_fraction(
+        &self,
+        visibility: &geo::Polygon<f64>,
+        _: BooleanBackend,
+    ) -> VisibleFraction<Self::Part> {
+        let part = geo::MultiLineString(
+            self.0
+                .iter()
+                .flat_map(|line_string| clip(visibility, line_string.lines()).0)
+                .collect(),
+        );
+        fraction(length(&part), length(self), part)
+    }
+}
+
+impl VisibilityPolygon {
+    /// Returns the part of the target seen from the viewpoint, polygons being intersected with the given boolean
+    /// operations.
+    ///
+    /// The visibility polygon can be computed once and reused for many targets.
+    pub fn visible_fraction<G: Target + ?Sized>(
+        &self,
+        target: &G,
+        backend: BooleanBackend,
+    ) -> VisibleFraction<G::Part> {
+        target.visible_fraction(&self.polygon, backend)
+    }
+}
+
+/// Returns the part of the target seen from the viewpoint amongst the obstacles with the given boolean operations.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon, Coord, Line};
+/// use geo_visibility::{visible_fraction, BooleanBackend};
+///
+/// let room = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+///     interiors: [[(x: 4.0, y: 4.0), (x: 6.0, y: 4.0), (x: 6.0, y: 6.0), (x: 4.0, y: 6.0)]],
+/// );
+/// // an exit sign along the right wall, partly hidden by the pillar
+/// let sign = Line::new(Coord { x: 10.0, y: 0.0 }, Coord { x: 10.0, y: 10.0 });
+///
+/// let seen = visible_fraction(point!(x: 1.0, y: 5.0), &room, &sign, BooleanBackend::default());
+///
+/// assert!((seen.measure - 4.0).abs() < 1E-6);
+/// assert!((seen.ratio - 0.4).abs() < 1E-6);
+/// assert_eq!(seen.part.0.len(), 2);
+/// ```
+pub fn visible_fraction<T, G>(
+    viewpoint: geo::Point<f64>,
+    obstacles: &T,
+    target: &G,
+    backend: BooleanBackend,
+) -> VisibleFraction<G::Part>
+where
+    T: Obstacles + ?Sized,
+    G: Target + ?Sized,
+{
+    VisibilityPolygon::new(viewpoint, obstacles).visible_fraction(target, backend)
+}
+
+fn fraction<G>(measure: f64, total: f64, part: G) -> VisibleFraction<G> {
+    VisibleFraction {
+        part,
+        measure,
+        ratio: if total > 0.0 { measure / total } else { 0.0 },
+    }
+}
+
+/// Returns the parts of the lines touching the polygon, consecutive parts being joined.
+fn clip(
+    polygon: &geo::Polygon<f64>,
+    lines: impl Iterator<Item = geo::Line<f64>>,
+) -> geo::MultiLineString<f64> {
+    let mut parts: Vec<geo::LineString<f64>> = Vec::new();
+    for line in lines {
+        for piece in visible_pieces(polygon, &line) {
+            match parts.last_mut() {
+                Some(last) if last.0.last() == Some(&piece.start) => last.0.push(piece.end),
+                _ => parts.push(geo::LineString(vec![piece.start, piece.end])),
+            }
+        }
+    }
+    geo::MultiLineString(parts)
+}
+
+/// Splits the line where it crosses the polygon edges and keeps the pieces touching the polygon.
+fn visible_pieces(polygon: &geo::Polygon<f64>, line: &geo::Line<f64>) -> Vec<geo::Line<f64>> {
+    let delta = line.delta();
+    let squared_length = delta.x * delta.x + delta.y * delta.y;
+    if squared_length == 0.0 {
+        return Vec::new();
+    }
+    let position = |point: geo::Coord<f64>| {
+        let offset = point - line.start;
+        ((offset.x * delta.x + offset.y * delta.y) / squared_length).clamp(0.0, 1.0)
+    };
+
+    let mut positions = vec![0.0, 1.0];
+    for edge in polygon.exterior().lines() {
+        match line_intersection(*line, edge) {
+            Some(LineIntersection::SinglePoint { intersection, .. }) => {
+                positions.push(position(intersection))
+            }
+            Some(LineIntersection::Collinear { intersection }) => {
+                positions.push(position(intersection.start));
+                positions.push(position(intersection.end));
+            }
+            None => {}
+        }
+    }
+    positions.sort_by(f64::total_cmp);
+    positions.dedup_by(|a, b| (*a - *b).abs() < 1E-12);
+
+    let tolerance = 1E-9 * (1.0 + line.start.x.abs().max(line.start.y.abs()));
+    let mut pieces: Vec<geo::Line<f64>> = Vec::new();
+    for pair in positions.windows(2) {
+        let middle = line.start + delta * ((pair[0] + pair[1]) / 2.0);
+        if Euclidean::distance(&geo::Point::from(middle), polygon) > tolerance {
+            continue;
+        }
+        let (start, end) = (line.start + delta * pair[0], line.start + delta * pair[1]);
+        match pieces.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => pieces.push(geo::Line::new(start, end)),
+        }
+    }
+    pieces
+}
+
+fn length(lines: &geo::MultiLineString<f64>) -> f64 {
+    lines
+        .0
+        .iter()
+        .flat_map(|line_string| line_string.lines())
+        .map(|line| length_of_line(&line))
+        .sum()
+}
+
+fn length_of_line(line: &geo::Line<f64>) -> f64 {
+    Euclidean::distance(geo::Point::from(line.start), geo::Point::from(line.end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, point, polygon};
+
+    fn room() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 4.0, y: 4.0),
+                (x: 6.0, y: 4.0),
+                (x: 6.0, y: 6.0),
+                (x: 4.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn measure_the_visible_part_of_polygons() {
+        let visibility = VisibilityPolygon::new(point!(x: 1.0, y: 5.0), &room());
+
+        let hidden =
+            polygon![(x: 8.0, y: 4.0), (x: 9.0, y: 4.0), (x: 9.0, y: 6.0), (x: 8.0, y: 6.0)];
+        let seen = polygon![(x: 8.0, y: 0.0), (x: 9.0, y: 0.0), (x: 9.0, y: 1.0), (x: 8.0, y: 1.0)];
+        // the ray going past the pillar corner crosses the square diagonal from (7, 3) to (10, 2)
+        let halved =
+            polygon![(x: 7.0, y: 2.0), (x: 10.0, y: 2.0), (x: 10.0, y: 3.0), (x: 7.0, y: 3.0)];
+
+        for backend in [BooleanBackend::default(), BooleanBackend::Geo] {
+            assert_eq!(visibility.visible_fraction(&hidden, backend).ratio, 0.0);
+            assert!((visibility.visible_fraction(&seen, backend).ratio - 1.0).abs() < 1E-6);
+            let fraction = visibility.visible_fraction(&halved, backend);
+            assert!((fraction.measure - 1.5).abs() < 1E-2);
+            assert!((fraction.ratio - 0.5).abs() < 1E-2);
+        }
+    }
+
+    #[test]
+    fn join_the_visible_parts_of_a_line_string() {
+        let visibility = VisibilityPolygon::new(point!(x: 1.0, y: 5.0), &room());
+        // along the bottom and right walls, hidden between y = 2 and y = 8
+        let walls = line_string![(x: 5.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0)];
+
+        let fraction = visibility.visible_fraction(&walls, BooleanBackend::default());
+
+        assert_eq!(fraction.part.0.len(), 2);
+        assert_eq!(fraction.part.0[0].0.len(), 3);
+        assert!((fraction.measure - 9.0).abs() < 1E-6);
+        assert!((fraction.ratio - 0.6).abs() < 1E-6);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::star_polygon::StarPolygon;
+use crate::visibility::VisibilityPolygon;
+use crate::visibility_matrix::line_of_sight;
+use rstar::{Envelope, PointDistance, RTree, RTreeObject, AABB};
+
+/// Number of hidden candidates after which the visibility polygon is computed to test the remaining ones.
+const HIDDEN_CANDIDATES: usize = 8;
+
+/// Returns the `k` points of interest closest to the viewpoint amongst those it sees, closest first.
+///
+/// Points of interest are visited by increasing distance and the search stops as soon as `k` of them are visible.
+/// The first candidates are tested with lines of sight, and if many of them turn out to be hidden,
+/// the visibility polygon of the viewpoint is computed once to test the remaining ones.
+/// The position of a point of interest is the centre of its envelope.
+///
+/// # Example
+///
+/// ```
+/// use geo::{point, polygon};
+/// use geo_visibility::visible_nearest_neighbors;
+/// use rstar::RTree;
+///
+/// let domain = polygon![
+///     (x: 0.0, y: 0.0),
+///     (x: 4.0, y: 0.0),
+///     (x: 4.0, y: 1.0),
+///     (x: 1.0, y: 1.0),
+///     (x: 1.0, y: 4.0),
+///     (x: 0.0, y: 4.0),
+/// ];
+/// let pois = RTree::bulk_load(vec![point!(x: 1.5, y: 1.5), point!(x: 3.5, y: 0.5)]);
+///
+/// let nearest = visible_nearest_neighbors(point!(x: 2.0, y: 0.5), &domain, &pois, 1);
+///
+/// assert_eq!(nearest, vec![&point!(x: 3.5, y: 0.5)]);
+/// ```
+pub fn visible_nearest_neighbors<'a, T, P>(
+    viewpoint: geo::Point<f64>,
+    obstacles: &T,
+    pois: &'a RTree<P>,
+    k: usize,
+) -> Vec<&'a P>
+where
+    T: Obstacles + ?Sized,
+    P: RTreeObject<Envelope = AABB<geo::Point<f64>>> + PointDistance,
+{
+    let mut nearest = Vec::with_capacity(k);
+    if k == 0 {
+        return nearest;
+    }
+
+    // lines of sight go through the raw segments, which are only extracted for the visibility polygon
+    let walls = obstacles.segments();
+    let mut star: Option<StarPolygon> = None;
+    let mut hidden = 0;
+    for poi in pois.nearest_neighbor_iter(&viewpoint) {
+        let position = poi.envelope().center();
+        let visible = match &star {
+            Some(star) => star.contains(&position),
+            None => line_of_sight(&viewpoint, &position, &walls),
+        };
+
+        if visible {
+            nearest.push(poi);
+            if nearest.len() == k {
+                break;
+            }
+        } else {
+            hidden += 1;
+            if hidden == HIDDEN_CANDIDATES {
+                let segments = ObstacleSegments::new(obstacles);
+                let visibility = VisibilityPolygon::among(viewpoint, &segments).polygon;
+                star = Some(StarPolygon::new(viewpoint, &visibility));
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{point, polygon};
+    use rstar::primitives::GeomWithData;
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn skip_hidden_points_of_interest() {
+        let pois = RTree::bulk_load(vec![
+            GeomWithData::new(point!(x: 8.0, y: 5.0), "behind"),
+            GeomWithData::new(point!(x: 1.0, y: 9.5), "far"),
+            GeomWithData::new(point!(x: 1.0, y: 1.0), "near"),
+        ]);
+
+        let nearest = visible_nearest_neighbors(point!(x: 1.0, y: 5.0), &domain(), &pois, 2);
+
+        let names: Vec<_> = nearest.iter().map(|poi| poi.data).collect();
+        assert_eq!(names, vec!["near", "far"]);
+    }
+
+    #[test]
+    fn do_not_see_through_a_corner_of_raw_segments() {
+        let walls = vec![
+            geo::Line::new(geo::Coord { x: 2.0, y: 2.0 }, geo::Coord { x: 3.0, y: 0.5 }),
+            geo::Line::new(geo::Coord { x: 2.0, y: 2.0 }, geo::Coord { x: 1.0, y: 3.5 }),
+        ];
+        let pois = RTree::bulk_load(vec![
+            GeomWithData::new(point!(x: 3.0, y: 3.0), "behind the corner"),
+            GeomWithData::new(point!(x: 0.5, y: 4.5), "farther aside"),
+        ]);
+
+        let nearest = visible_nearest_neighbors(point!(x: 1.0, y: 1.0), walls.as_slice(), &pois, 1);
+
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest[0].data, "farther aside");
+    }
+
+    #[test]
+    fn switch_to_the_visibility_polygon_after_many_hidden_points() {
+        let mut points: Vec<_> = (0..20)
+            .map(|i| point!(x: 8.0 + i as f64 * 0.05, y: 5.0))
+            .collect();
+        points.push(point!(x: 1.0, y: 0.5));
+        let pois = RTree::bulk_load(points);
+
+        let nearest = visible_nearest_neighbors(point!(x: 1.0, y: 5.0), &domain(), &pois, 3);
+
+        assert_eq!(nearest, vec![&point!(x: 1.0, y: 0.5)]);
+        assert!(visible_nearest_neighbors(point!(x: 1.0, y: 5.0), &domain(), &pois, 0).is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::guard_placement::{GuardCandidates, GuardCandidatesError, GuardPlacement};
+use crate::visibility_graph::VisibilityGraph;
+
+/// Error returned when no watchman route can be found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchmanRouteError {
+    /// The candidate viewpoints cannot be generated.
+    Candidates(GuardCandidatesError),
+    /// The obstacles separate some viewpoints from the others.
+    Unreachable,
+}
+
+impl std::fmt::Display for WatchmanRouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchmanRouteError::Candidates(error) => write!(f, "invalid candidates: {}", error),
+            WatchmanRouteError::Unreachable => {
+                write!(f, "some viewpoints are not reachable from the others")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WatchmanRouteError {}
+
+impl From<GuardCandidatesError> for WatchmanRouteError {
+    fn from(error: GuardCandidatesError) -> Self {
+        WatchmanRouteError::Candidates(error)
+    }
+}
+
+/// Approximate watchman route: a closed walk from which the domain is seen.
+///
+/// Viewpoints covering the domain are picked greedily amongst candidates, then visited in the order given by a
+/// nearest neighbour tour improved with 2-opt, each leg being a shortest path amongst the obstacles.
+///
+/// # Example
+///
+/// ```
+/// use geo::polygon;
+/// use geo_visibility::{GuardCandidates, WatchmanRoute};
+///
+/// let domain = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+///     interiors: [[(x: 3.0, y: 4.0), (x: 7.0, y: 4.0), (x: 7.0, y: 6.0), (x: 3.0, y: 6.0)]],
+/// );
+///
+/// let watchman = WatchmanRoute::new(&domain, &GuardCandidates::Grid { spacing: 1.0 }, 1.0).unwrap();
+///
+/// assert!(watchman.route.is_closed());
+/// assert!(watchman.covered_ratio > 0.99);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchmanRoute {
+    /// Closed route going through every viewpoint.
+    pub route: geo::LineString<f64>,
+    /// Viewpoints in the order they are visited.
+    pub viewpoints: Vec<geo::Point<f64>>,
+    /// Fraction of the domain seen from the viewpoints.
+    pub covered_ratio: f64,
+}
+
+impl WatchmanRoute {
+    /// Computes a route seeing the target fraction of the domain from viewpoints picked amongst the candidates.
+    ///
+    /// Fails if the candidates cannot be generated or if the obstacles separate some viewpoints from the others.
+    pub fn new(
+        domain: &geo::Polygon<f64>,
+        candidates: &GuardCandidates,
+        target_ratio: f64,
+    ) -> Result<Self, WatchmanRouteError> {
+        let placement = GuardPlacement::greedy(domain, candidates, target_ratio)?;
+        let graph = VisibilityGraph::with_points(domain, &placement.guards);
+        let nodes = placement
+            .guards
+            .iter()
+            .map(|guard| graph.node(guard).ok_or(WatchmanRouteError::Unreachable))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let trees: Vec<_> = nodes
+            .iter()
+            .map(|node| graph.shortest_path_tree(*node))
+            .collect();
+        let distances: Vec<Vec<f64>> = trees
+            .iter()
+            .map(|(distances, _)| nodes.iter().map(|node| distances[*node]).collect())
+            .collect();
+        if distances
+            .iter()
+            .flatten()
+            .any(|distance| distance.is_infinite())
+        {
+            return Err(WatchmanRouteError::Unreachable);
+        }
+
+        let order = improve_tour(nearest_neighbor_tour(&distances), &distances);
+
+        let mut coords = Vec::new();
+        for (i, from) in order.iter().enumerate() {
+            let to = order[(i + 1) % order.len()];
+            let (_, previous) = &trees[*from];
+            let mut leg = vec![nodes[to]];
+            while let Some(node) = previous[*leg.last().unwrap()] {
+                leg.push(node);
+            }
+            leg.reverse();
+            if !coords.is_empty() {
+                leg.remove(0);
+            }
+            coords.extend(leg.into_iter().map(|node| graph.nodes()[node].0));
+        }
+        if coords.len() == 1 {
+            coords.push(coords[0]);
+        }
+
+        Ok(Self {
+            route: geo::LineString(coords),
+            viewpoints: order.iter().map(|index| placement.guards[*index]).collect(),
+            covered_ratio: placement.covered_ratio,
+        })
+    }
+}
+
+/// Builds a tour by always going to the closest unvisited viewpoint.
+fn nearest_neighbor_tour(distances: &[Vec<f64>]) -> Vec<usize> {
+    let mut tour = Vec::with_capacity(distances.len());
+    let mut visited = vec![false; distances.len()];
+    let mut current = 0;
+    while current < distances.len() {
+        tour.push(current);
+        visited[current] = true;
+        current = (0..distances.len())
+            .filter(|next| !visited[*next])
+            .min_by(|a, b| distances[current][*a].total_cmp(&distances[current][*b]))
+            .unwrap_or(distances.len());
+    }
+    tour
+}
+
+/// Reverses parts of the tour as long as it shortens it.
+fn improve_tour(mut tour: Vec<usize>, distances: &[Vec<f64>]) -> Vec<usize> {
+    let n = tour.len();
+    let mut improved = n > 3;
+    while improved {
+        improved = false;
+        for i in 0..n - 1 {
+            for j in i + 2..n {
+                let (a, b) = (tour[i], tour[i + 1]);
+                let (c, d) = (tour[j], tour[(j + 1) % n]);
+                if a == d {
+                    continue;
+                }
+                if distances[a][c] + distances[b][d] < distances[a][b] + distances[c][d] - 1E-9 {
+                    tour[i + 1..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+    tour
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{polygon, Intersects};
+
+    fn domain() -> geo::Polygon<f64> {
+        polygon!(
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 3.0, y: 4.0),
+                (x: 7.0, y: 4.0),
+                (x: 7.0, y: 6.0),
+                (x: 3.0, y: 6.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn walk_around_a_hole() {
+        let domain = domain();
+        let watchman = WatchmanRoute::new(&domain, &GuardCandidates::Vertices, 1.0).unwrap();
+
+        assert!(watchman.viewpoints.len() > 1);
+        assert!(watchman.covered_ratio > 0.999);
+        assert!(watchman.route.is_closed());
+        for line in watchman.route.lines() {
+            assert!(domain.intersects(&line.start));
+            assert!(domain.intersects(&((line.start + line.end) / 2.0)));
+        }
+    }
+
+    #[test]
+    fn stay_in_place_in_a_convex_room() {
+        let room = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 4.0, y: 0.0),
+            (x: 4.0, y: 4.0),
+            (x: 0.0, y: 4.0),
+        ];
+        let watchman =
+            WatchmanRoute::new(&room, &GuardCandidates::Grid { spacing: 1.0 }, 1.0).unwrap();
+
+        assert_eq!(watchman.viewpoints.len(), 1);
+        assert_eq!(watchman.route.0.len(), 2);
+        assert!(watchman.covered_ratio > 0.999);
+    }
+
+    #[test]
+    fn reject_invalid_candidates() {
+        assert_eq!(
+            WatchmanRoute::new(&domain(), &GuardCandidates::Grid { spacing: -1.0 }, 1.0),
+            Err(WatchmanRouteError::Candidates(
+                GuardCandidatesError::InvalidSpacing(-1.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn shorten_crossing_tours() {
+        let distances = vec![
+            vec![0.0, 1.0, 2.0_f64.sqrt(), 1.0],
+            vec![1.0, 0.0, 1.0, 2.0_f64.sqrt()],
+            vec![2.0_f64.sqrt(), 1.0, 0.0, 1.0],
+            vec![1.0, 2.0_f64.sqrt(), 1.0, 0.0],
+        ];
+
+        assert_eq!(improve_tour(vec![0, 2, 1, 3], &distances), vec![0, 1, 2, 3]);
+    }
+}
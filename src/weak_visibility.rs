@@ -0,0 +1,259 @@
+use crate::boolean::BooleanBackend;
+use crate::obstacles::{ObstacleSegments, Obstacles};
+use crate::visibility::{EdgeSource, Visibility, VisibilityPolygon};
+use geo::line_intersection::{line_intersection, LineIntersection};
+use geo::{Distance, Euclidean};
+
+/// The polyline sees every point seen from at least one of its points, that is its weak visibility region.
+///
+/// Along each segment of the polyline, the visibility polygon only changes combinatorially where the line
+/// through two obstacle vertices crosses the segment. Between two such critical points, the region seen is the
+/// visibility polygon from any point in between along with the triangles swept by its windows, which rotate
+/// around the obstacle vertices they go past.
+/// The result is thus exact up to the precision of the union, at the cost of one visibility polygon per pair
+/// of obstacle vertices whose line crosses the polyline.
+///
+/// # Example
+///
+/// ```
+/// use geo::{line_string, polygon, Area};
+/// use geo_visibility::Visibility;
+///
+/// let domain = polygon!(
+///     exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+///     interiors: [[(x: 3.0, y: 4.0), (x: 7.0, y: 4.0), (x: 7.0, y: 6.0), (x: 3.0, y: 6.0)]],
+/// );
+/// let corridor = line_string![(x: 1.0, y: 1.0), (x: 1.0, y: 9.0)];
+///
+/// let visibility = corridor.visibility(&domain);
+///
+/// // the triangle behind the hole, between the rays from both ends of the corridor, stays hidden
+/// assert!((visibility.unsigned_area() - 90.0).abs() < 1E-2);
+/// ```
+impl<T: Obstacles + ?Sized> Visibility<T> for geo::LineString<f64> {
+    type Output = geo::MultiPolygon<f64>;
+
+    fn visibility(&self, obstacles: &T) -> geo::MultiPolygon<f64> {
+        polyline_visibility(self, obstacles, BooleanBackend::default())
+    }
+}
+
+/// Computes the region seen from the points of the polyline with the given boolean operations.
+///
+/// This is what [`Visibility::visibility`] returns for a polyline, with the default [`BooleanBackend`].
+///
+/// # Example
+///
+/// ```
+/// use geo::{line_string, polygon, Area};
+/// use geo_visibility::{polyline_visibility, BooleanBackend};
+///
+/// let domain = polygon![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)];
+/// let corridor = line_string![(x: 1.0, y: 1.0), (x: 1.0, y: 9.0)];
+///
+/// let visibility = polyline_visibility(&corridor, &domain, BooleanBackend::Geo);
+///
+/// assert!((visibility.unsigned_area() - 100.0).abs() < 1E-9);
+/// ```
+pub fn polyline_visibility<T: Obstacles + ?Sized>(
+    polyline: &geo::LineString<f64>,
+    obstacles: &T,
+    backend: BooleanBackend,
+) -> geo::MultiPolygon<f64> {
+    let segments = ObstacleSegments::new(obstacles);
+    let vertices = obstacle_vertices(&segments.segments);
+
+    polyline
+        .lines()
+        .fold(geo::MultiPolygon(Vec::new()), |visibility, line| {
+            backend.union(
+                &visibility,
+                &segment_visibility(&line, &segments, &vertices, backend),
+            )
+        })
+}
+
+/// Returns the region seen from the points of the segment.
+fn segment_visibility(
+    segment: &geo::Line<f64>,
+    segments: &ObstacleSegments,
+    vertices: &[geo::Coord<f64>],
+    backend: BooleanBackend,
+) -> geo::MultiPolygon<f64> {
+    if segment.start == segment.end {
+        return geo::MultiPolygon(vec![
+            VisibilityPolygon::among(segment.start.into(), segments).polygon,
+        ]);
+    }
+
+    // positions along the segment where the line through two obstacle vertices crosses it
+    let mut positions = vec![0.0, 1.0];
+    for (i, a) in vertices.iter().enumerate() {
+        for b in &vertices[i + 1..] {
+            if let Some(t) = crossing(segment, a, b) {
+                positions.push(t);
+            }
+        }
+    }
+    positions.sort_by(f64::total_cmp);
+    positions.dedup_by(|a, b| (*a - *b).abs() < 1E-12);
+
+    let mut polygons = Vec::new();
+    for pair in positions.windows(2) {
+        let (start, end) = (at(segment, pair[0]), at(segment, pair[1]));
+        let middle = at(segment, (pair[0] + pair[1]) / 2.0);
+        let visibility = VisibilityPolygon::among(middle.into(), segments);
+
+        // windows pivot around the vertex they go past while the viewpoint moves along the interval
+        for (edge, source) in visibility.lines() {
+            if !matches!(source, EdgeSource::Window(_)) {
+                continue;
+            }
+            let pivot = if distance(&edge.start, &middle) < distance(&edge.end, &middle) {
+                edge.start
+            } else {
+                edge.end
+            };
+            if let (Some(first), Some(last)) = (
+                cast(&start, &pivot, &segments.segments),
+                cast(&end, &pivot, &segments.segments),
+            ) {
+                polygons.push(geo::Polygon::new(
+                    geo::LineString(vec![pivot, first, last]),
+                    Vec::new(),
+                ));
+            }
+        }
+        polygons.push(visibility.polygon);
+    }
+
+    polygons
+        .iter()
+        .fold(geo::MultiPolygon(Vec::new()), |union, polygon| {
+            backend.union(&union, polygon)
+        })
+}
+
+/// Returns the distinct endpoints of the segments.
+fn obstacle_vertices(segments: &[geo::Line<f64>]) -> Vec<geo::Coord<f64>> {
+    let mut vertices: Vec<_> = segments
+        .iter()
+        .flat_map(|segment| [segment.start, segment.end])
+        .collect();
+    vertices.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    vertices.dedup();
+    vertices
+}
+
+/// Returns the position along the segment where the line through the two points strictly crosses it.
+fn crossing(segment: &geo::Line<f64>, a: &geo::Coord<f64>, b: &geo::Coord<f64>) -> Option<f64> {
+    let delta = segment.delta();
+    let direction = *b - *a;
+    let det = direction.x * delta.y - direction.y * delta.x;
+    if det == 0.0 {
+        return None;
+    }
+    let offset = segment.start - *a;
+    let t = (direction.y * offset.x - direction.x * offset.y) / det;
+    Some(t).filter(|t| *t > 0.0 && *t < 1.0)
+}
+
+/// Returns the point at the given position along the segment.
+fn at(segment: &geo::Line<f64>, t: f64) -> geo::Coord<f64> {
+    segment.start + segment.delta() * t
+}
+
+/// Returns the first point where the ray from the origin going past the pivot hits an obstacle beyond it.
+fn cast(
+    origin: &geo::Coord<f64>,
+    pivot: &geo::Coord<f64>,
+    segments: &[geo::Line<f64>],
+) -> Option<geo::Coord<f64>> {
+    let direction = *pivot - *origin;
+    let length = direction.x.hypot(direction.y);
+    if length == 0.0 {
+        return None;
+    }
+    let reach = segments
+        .iter()
+        .flat_map(|segment| [segment.start, segment.end])
+        .map(|point| distance(&point, pivot))
+        .fold(0.0, f64::max)
+        * 2.0
+        + 1.0;
+    let ray = geo::Line::new(*pivot, *pivot + direction / length * reach);
+    let tolerance = 1E-9 * reach;
+
+    segments
+        .iter()
+        .filter_map(|segment| match line_intersection(ray, *segment) {
+            Some(LineIntersection::SinglePoint { intersection, .. }) => Some(intersection),
+            Some(LineIntersection::Collinear { intersection }) => {
+                Some(if intersection.start == *pivot {
+                    intersection.end
+                } else {
+                    intersection.start
+                })
+            }
+            None => None,
+        })
+        .filter(|hit| distance(hit, pivot) > tolerance)
+        .min_by(|a, b| distance(a, pivot).total_cmp(&distance(b, pivot)))
+}
+
+fn distance(a: &geo::Coord<f64>, b: &geo::Coord<f64>) -> f64 {
+    Euclidean::distance(geo::Point::from(*a), geo::Point::from(*b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, Area, Contains, Coord, Line};
+
+    fn room_with_a_gap() -> Vec<geo::Line<f64>> {
+        [
+            [[0.0, 0.0], [10.0, 0.0]],
+            [[10.0, 0.0], [10.0, 10.0]],
+            [[10.0, 10.0], [0.0, 10.0]],
+            [[0.0, 10.0], [0.0, 0.0]],
+            [[0.0, 5.0], [4.9, 5.0]],
+            [[5.1, 5.0], [10.0, 5.0]],
+        ]
+        .iter()
+        .map(|[[x1, y1], [x2, y2]]| Line::new(Coord { x: *x1, y: *y1 }, Coord { x: *x2, y: *y2 }))
+        .collect()
+    }
+
+    #[test]
+    fn see_through_a_gap_from_the_middle_of_a_segment() {
+        let walls = room_with_a_gap();
+        let corridor = line_string![(x: 1.0, y: 1.0), (x: 9.0, y: 1.0)];
+
+        let visibility = corridor.visibility(walls.as_slice());
+
+        // only the points in the middle of the corridor see through the gap towards the top wall
+        let target = geo::Point::new(5.0, 9.0);
+        assert!(visibility.contains(&target));
+        let ends = geo::MultiPoint(vec![geo::Point::new(1.0, 1.0), geo::Point::new(9.0, 1.0)]);
+        assert!(!ends.visibility(walls.as_slice()).contains(&target));
+
+        // the region is the union of what every point of the corridor sees
+        let samples: geo::MultiPoint<f64> = (0..=200)
+            .map(|i| geo::Point::new(1.0 + i as f64 * 0.04, 1.0))
+            .collect();
+        let sampled = samples.visibility(walls.as_slice()).unsigned_area();
+        assert!(visibility.unsigned_area() >= sampled - 1E-2);
+        assert!(visibility.unsigned_area() <= sampled + 5E-2);
+    }
+
+    #[test]
+    fn see_from_every_point() {
+        let walls = room_with_a_gap();
+        let points = [geo::Point::new(1.0, 1.0), geo::Point::new(5.0, 9.0)];
+
+        let polygons = points[..].visibility(walls.as_slice());
+
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[1], points[1].visibility(walls.as_slice()));
+    }
+}